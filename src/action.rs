@@ -1,14 +1,105 @@
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::fmt;
 
 fn default_distance() -> f32 {
     100.0
 }
 
-#[derive(Debug, Copy, Clone, Deserialize)]
+// Slots can be named or numbered, numbers are treated as their string form
+pub fn deserialize_slot<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Slot {
+        Number(u32),
+        Name(String),
+    }
+
+    Ok(match Slot::deserialize(deserializer)? {
+        Slot::Number(n) => n.to_string(),
+        Slot::Name(name) => name,
+    })
+}
+
+// Inputs that are held down while flying, rather than triggered once
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize)]
+pub enum FlyInput {
+    Forward,
+    Backward,
+    Left,
+    Right,
+    Up,
+    Down,
+    Fast,
+    Slow,
+}
+
+// The two position handlers, which share the position actions. Named as in the saved positions
+// file and the event stream.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum PositionKind {
+    #[serde(rename = "position")]
+    Position,
+    #[serde(rename = "look at position")]
+    LookAt,
+}
+
+impl PositionKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PositionKind::Position => "position",
+            PositionKind::LookAt => "look at position",
+        }
+    }
+}
+
+impl fmt::Display for PositionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
 pub enum Action {
     ToggleActive {},
     StorePosition {},
     RestorePosition {},
+    StorePositionSlot {
+        #[serde(deserialize_with = "deserialize_slot")]
+        slot: String,
+        #[serde(default)]
+        label: Option<String>,
+    },
+    RestorePositionSlot {
+        #[serde(deserialize_with = "deserialize_slot")]
+        slot: String,
+        // Only restore with this handler, rather than every position handler with the slot
+        #[serde(default)]
+        handler: Option<PositionKind>,
+    },
+    NextPositionSlot {},
+    PreviousPositionSlot {},
+    UndoTeleport {},
+    RedoTeleport {},
+    ToggleFlyMode {},
+    FlyInputPressed {
+        input: FlyInput,
+    },
+    FlyInputReleased {
+        input: FlyInput,
+    },
+    StartRecording {},
+    StopRecording {},
+    StartGhost {},
+    StopGhost {},
+    StartSegment {
+        #[serde(default)]
+        name: Option<String>,
+    },
+    StopSegment {},
+    PrintStatus {},
     SkipCutscene {},
     ResetSkipCutsceneTracker {},
     Forward {
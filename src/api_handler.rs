@@ -0,0 +1,312 @@
+use crate::action::{Action, PositionKind};
+use crate::capabilities::Feature;
+use crate::event::{Event, EventCategory};
+use crate::handler::Handler;
+use crate::memory_backend::MemoryBackend;
+use crate::process_details::ProcessDetails;
+use crate::saved_positions::SavedSlots;
+use crate::tracked_vector::TrackedVector;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io;
+use std::rc::Rc;
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use tiny_http::{Header, Method, Request, Response, Server};
+
+#[derive(Debug, Clone, Serialize)]
+struct CutsceneTimeSaved {
+    rta: f32,
+    igt: f32,
+}
+
+// Everything served from GET /state
+#[derive(Debug, Clone, Serialize)]
+struct ApiState {
+    // False while the game isn't running, the rest of the state is from when it last was
+    connected: bool,
+    game: String,
+    version: String,
+    capabilities: Vec<&'static str>,
+    position: Option<[f32; 3]>,
+    // Handler name -> slots
+    saved_slots: HashMap<PositionKind, SavedSlots>,
+    selected_positions: HashMap<PositionKind, Option<[f32; 3]>>,
+    cutscene_time_saved: CutsceneTimeSaved,
+    cutscene_id: Option<u32>,
+}
+
+// Serves the state of tomb-helper as JSON on localhost and accepts actions, for the lifetime of
+// the connection to the game. The server runs on its own thread and only sees a copy of the state
+// which is kept up to date from events.
+pub struct ApiHandler {
+    state: Arc<Mutex<ApiState>>,
+    position: Option<TrackedVector>,
+    memory: Rc<dyn MemoryBackend>,
+    server: Arc<Server>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ApiHandler {
+    pub fn new(
+        details: &ProcessDetails,
+        base_addr: &usize,
+        memory: &Rc<dyn MemoryBackend>,
+        port: &u16,
+        actions: &Sender<Action>,
+    ) -> Option<ApiHandler> {
+        println!("Loading HTTP API handler...");
+
+        let server = match Server::http(("127.0.0.1", *port)) {
+            Ok(server) => Arc::new(server),
+            Err(msg) => {
+                eprintln!("Error: Could not start HTTP API on port {}: {}", port, msg);
+                return None;
+            }
+        };
+        println!("Serving HTTP API on http://127.0.0.1:{}/", port);
+
+        let position = TrackedVector::position(&details.address_offsets, *base_addr);
+
+        let state = Arc::new(Mutex::new(ApiState {
+            connected: false,
+            game: details.name.clone(),
+            version: details.version.version.clone(),
+            capabilities: Feature::all()
+                .into_iter()
+                .filter(|feature| feature.missing_addresses(details).is_empty())
+                .map(|feature| feature.name())
+                .collect(),
+            position: None,
+            saved_slots: HashMap::new(),
+            selected_positions: HashMap::new(),
+            cutscene_time_saved: CutsceneTimeSaved { rta: 0.0, igt: 0.0 },
+            cutscene_id: None,
+        }));
+
+        let thread_server = server.clone();
+        let thread_state = state.clone();
+        let thread_actions = actions.clone();
+        let thread = thread::spawn(move || {
+            for request in thread_server.incoming_requests() {
+                respond(request, &thread_state, &thread_actions);
+            }
+        });
+
+        Some(ApiHandler {
+            state,
+            position,
+            memory: memory.clone(),
+            server,
+            thread: Some(thread),
+        })
+    }
+}
+
+fn respond(mut request: Request, state: &Mutex<ApiState>, actions: &Sender<Action>) {
+    let method = request.method().clone();
+    let url = request.url().to_string();
+    let (status, body) = handle_request(
+        &method,
+        &url,
+        || {
+            let mut body = String::new();
+            request.as_reader().read_to_string(&mut body).map(|_| body)
+        },
+        state,
+        actions,
+    );
+
+    let mut response = Response::from_string(body).with_status_code(status);
+    if status == 200 {
+        let json = Header::from_bytes(&b"Content-Type"[..], &b"application/json"[..]).unwrap();
+        response = response.with_header(json);
+    }
+    request
+        .respond(response)
+        .unwrap_or_else(|msg| eprintln!("Error: Could not respond to HTTP API request: {}", msg));
+}
+
+// The status code and body of the response, the request body is only read for actions
+fn handle_request(
+    method: &Method,
+    url: &str,
+    read_body: impl FnOnce() -> io::Result<String>,
+    state: &Mutex<ApiState>,
+    actions: &Sender<Action>,
+) -> (u16, String) {
+    match (method, url) {
+        (Method::Get, "/state") => {
+            let state = state.lock().unwrap().clone();
+            (200, serde_json::to_string(&state).unwrap())
+        }
+        // Actions sent while disconnected would be thrown away when the game is next connected to
+        (Method::Post, "/action") if !state.lock().unwrap().connected => {
+            (503, "Not connected to the game".to_string())
+        }
+        (Method::Post, "/action") => {
+            let result = read_body()
+                .map_err(|e| e.to_string())
+                .and_then(|body| serde_json::from_str::<Action>(&body).map_err(|e| e.to_string()))
+                .and_then(|action| actions.send(action).map_err(|e| e.to_string()));
+            match result {
+                Ok(()) => (204, String::new()),
+                Err(msg) => (400, format!("Invalid action: {}", msg)),
+            }
+        }
+        _ => (404, "Not found".to_string()),
+    }
+}
+
+impl Drop for ApiHandler {
+    fn drop(&mut self) {
+        self.server.unblock();
+        if let Some(thread) = self.thread.take() {
+            thread.join().ok();
+        }
+    }
+}
+
+impl Handler for ApiHandler {
+    fn name(&self) -> &str {
+        "HTTP API"
+    }
+
+    fn status(&self) -> String {
+        format!("serving on http://{}/", self.server.server_addr())
+    }
+
+    fn on_connect(&mut self) -> Result<(), Box<dyn Error>> {
+        self.state.lock().unwrap().connected = true;
+        Ok(())
+    }
+
+    fn on_disconnect(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut state = self.state.lock().unwrap();
+        state.connected = false;
+        state.position = None;
+        Ok(())
+    }
+
+    fn rebind(&mut self, base_addr: usize, memory: &Rc<dyn MemoryBackend>) {
+        if let Some(position) = &mut self.position {
+            position.rebind(base_addr);
+        }
+        self.memory = memory.clone();
+    }
+
+    fn handle_tick(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(position) = &mut self.position {
+            self.state.lock().unwrap().position = position.fetch_all(&*self.memory);
+        }
+        Ok(())
+    }
+
+    fn handle_action(&mut self, _action: Action) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn subscriptions(&self) -> Vec<EventCategory> {
+        vec![EventCategory::Position, EventCategory::Cutscene]
+    }
+
+    fn handle_event(&mut self, event: &Event) -> Result<(), Box<dyn Error>> {
+        let mut state = self.state.lock().unwrap();
+        match event {
+            Event::SavedSlotsChanged { handler, slots } => {
+                state.saved_slots.insert(*handler, slots.clone());
+            }
+            Event::SelectedPositionChanged { handler, position } => {
+                state.selected_positions.insert(*handler, *position);
+            }
+            Event::CutsceneTimeSaved { rta, igt } => {
+                state.cutscene_time_saved = CutsceneTimeSaved { rta: *rta, igt: *igt };
+            }
+            Event::CutsceneChanged { id } => state.cutscene_id = *id,
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::saved_positions::SavedPosition;
+    use serde_json::Value;
+    use std::sync::mpsc::{self, Receiver};
+
+    fn api_state(connected: bool) -> Mutex<ApiState> {
+        Mutex::new(ApiState {
+            connected,
+            game: "Tomb Raider".to_string(),
+            version: "1.0".to_string(),
+            capabilities: vec!["position"],
+            position: Some([1.0, 2.0, 3.0]),
+            saved_slots: HashMap::new(),
+            selected_positions: HashMap::new(),
+            cutscene_time_saved: CutsceneTimeSaved { rta: 1.5, igt: 1.0 },
+            cutscene_id: None,
+        })
+    }
+
+    fn post_action(state: &Mutex<ApiState>, body: &str) -> ((u16, String), Receiver<Action>) {
+        let (tx, rx) = mpsc::channel();
+        let response = handle_request(&Method::Post, "/action", || Ok(body.to_string()), state, &tx);
+        (response, rx)
+    }
+
+    #[test]
+    fn serves_the_state_as_json() {
+        let state = api_state(true);
+        let slots = vec![("pit".to_string(), SavedPosition::new(4.0, 5.0, 6.0, None))]
+            .into_iter()
+            .collect::<SavedSlots>();
+        state.lock().unwrap().saved_slots.insert(PositionKind::Position, slots);
+        let (tx, _rx) = mpsc::channel();
+
+        let (status, body) = handle_request(&Method::Get, "/state", || unreachable!(), &state, &tx);
+
+        assert_eq!(status, 200);
+        let json: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["connected"], true);
+        assert_eq!(json["game"], "Tomb Raider");
+        assert_eq!(json["position"], serde_json::json!([1.0, 2.0, 3.0]));
+        assert_eq!(json["saved_slots"]["position"]["pit"]["x"], 4.0);
+        assert_eq!(json["cutscene_time_saved"]["rta"], 1.5);
+        assert_eq!(json["cutscene_id"], Value::Null);
+    }
+
+    #[test]
+    fn sends_posted_actions() {
+        let state = api_state(true);
+
+        let (response, actions) = post_action(&state, r#"{"StorePosition": {}}"#);
+
+        assert_eq!(response.0, 204);
+        assert!(matches!(actions.try_recv(), Ok(Action::StorePosition {})));
+    }
+
+    #[test]
+    fn rejects_invalid_actions() {
+        let state = api_state(true);
+
+        let ((status, _), actions) = post_action(&state, r#"{"Teleport": {}}"#);
+
+        assert_eq!(status, 400);
+        assert!(actions.try_recv().is_err());
+    }
+
+    #[test]
+    fn refuses_actions_while_disconnected() {
+        let state = api_state(false);
+
+        let ((status, body), actions) = post_action(&state, r#"{"StorePosition": {}}"#);
+
+        assert_eq!(status, 503);
+        assert_eq!(body, "Not connected to the game");
+        assert!(actions.try_recv().is_err());
+    }
+}
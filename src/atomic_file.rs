@@ -0,0 +1,13 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+// Writes to a temporary file first and then moves it into place, so that anything reading the
+// file (e.g. OBS or an overlay) never sees it half written
+pub fn write_atomically<P: AsRef<Path>>(path: P, contents: &str) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut temporary_path = path.as_os_str().to_owned();
+    temporary_path.push(".tmp");
+    fs::write(&temporary_path, contents)?;
+    fs::rename(&temporary_path, path)
+}
@@ -0,0 +1,192 @@
+use crate::memory_backend::MemoryBackend;
+use crate::process_details::{AddressType, ProcessDetails};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Feature {
+    Position,
+    Facing,
+    Velocity,
+    LookAt,
+    CameraRelativeMovement,
+    CameraPitchMovement,
+    CutsceneSkip,
+    CutsceneTimingGeneration,
+}
+
+impl Feature {
+    pub fn all() -> Vec<Feature> {
+        vec![
+            Feature::Position,
+            Feature::Facing,
+            Feature::Velocity,
+            Feature::LookAt,
+            Feature::CameraRelativeMovement,
+            Feature::CameraPitchMovement,
+            Feature::CutsceneSkip,
+            Feature::CutsceneTimingGeneration,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Feature::Position => "Position",
+            Feature::Facing => "Facing",
+            Feature::Velocity => "Velocity",
+            Feature::LookAt => "Look at",
+            Feature::CameraRelativeMovement => "Camera movement",
+            Feature::CameraPitchMovement => "Pitch movement",
+            Feature::CutsceneSkip => "Cutscene skip",
+            Feature::CutsceneTimingGeneration => "Timing generation",
+        }
+    }
+
+    // The addresses the corresponding handler needs, it won't be loaded without all of them
+    pub fn required_addresses(&self) -> Vec<AddressType> {
+        match self {
+            Feature::Position => vec![
+                AddressType::XPosition,
+                AddressType::YPosition,
+                AddressType::ZPosition,
+            ],
+            Feature::Facing => vec![
+                AddressType::XPosition,
+                AddressType::YPosition,
+                AddressType::ZPosition,
+                AddressType::XRotation,
+                AddressType::YRotation,
+                AddressType::ZRotation,
+            ],
+            Feature::Velocity => vec![
+                AddressType::XPosition,
+                AddressType::YPosition,
+                AddressType::ZPosition,
+                AddressType::XVelocity,
+                AddressType::YVelocity,
+                AddressType::ZVelocity,
+            ],
+            Feature::LookAt => vec![
+                AddressType::XLookAt,
+                AddressType::YLookAt,
+                AddressType::ZLookAt,
+            ],
+            Feature::CameraRelativeMovement => vec![
+                AddressType::XPosition,
+                AddressType::YPosition,
+                AddressType::ZPosition,
+                AddressType::CameraSin,
+                AddressType::CameraCos,
+            ],
+            Feature::CameraPitchMovement => vec![
+                AddressType::XPosition,
+                AddressType::YPosition,
+                AddressType::ZPosition,
+                AddressType::CameraRotationX,
+                AddressType::CameraRotationY,
+                AddressType::CameraRotationZ,
+            ],
+            Feature::CutsceneSkip => vec![
+                AddressType::CutscenePrompt,
+                AddressType::CutsceneStatus,
+                AddressType::CutsceneTimeline,
+                AddressType::CutsceneLength,
+                AddressType::CutsceneId,
+            ],
+            Feature::CutsceneTimingGeneration => vec![
+                AddressType::CutsceneTimeline,
+                AddressType::CutsceneId,
+                AddressType::CutscenePrompt,
+            ],
+        }
+    }
+
+    pub fn missing_addresses(&self, details: &ProcessDetails) -> Vec<AddressType> {
+        self.required_addresses()
+            .into_iter()
+            .filter(|address_type| !details.address_offsets.contains_key(address_type))
+            .collect()
+    }
+}
+
+const FEATURE_COLUMN_WIDTH: usize = 20;
+
+// Prints which features the connected version supports, and whether the pointer chains they use
+// currently resolve (many only do once a level is loaded)
+pub fn print_capability_report(details: &ProcessDetails, memory: &dyn MemoryBackend, base_addr: usize) {
+    println!("Capabilities of {} {}:", details.name, details.version.version);
+    for feature in Feature::all() {
+        let missing = feature.missing_addresses(details);
+        if !missing.is_empty() {
+            println!(
+                "  {:<width$}not available (no {:?})",
+                feature.name(),
+                missing,
+                width = FEATURE_COLUMN_WIDTH
+            );
+            continue;
+        }
+
+        let unresolved = feature
+            .required_addresses()
+            .into_iter()
+            .filter(|address_type| resolve(details, memory, base_addr, address_type).is_err())
+            .collect::<Vec<_>>();
+        if unresolved.is_empty() {
+            println!("  {:<width$}available", feature.name(), width = FEATURE_COLUMN_WIDTH);
+        } else {
+            println!(
+                "  {:<width$}available ({:?} not resolving yet)",
+                feature.name(),
+                unresolved,
+                width = FEATURE_COLUMN_WIDTH
+            );
+        }
+    }
+
+    let mut address_types = details.address_offsets.keys().collect::<Vec<_>>();
+    address_types.sort_by_key(|address_type| format!("{:?}", address_type));
+    println!("Addresses:");
+    for address_type in address_types {
+        match resolve(details, memory, base_addr, address_type) {
+            Ok(addr) => println!("  {:<width$}{:#x}", format!("{:?}", address_type), addr, width = FEATURE_COLUMN_WIDTH),
+            Err(msg) => println!(
+                "  {:<width$}does not resolve ({})",
+                format!("{:?}", address_type),
+                msg,
+                width = FEATURE_COLUMN_WIDTH
+            ),
+        }
+    }
+}
+
+fn resolve(
+    details: &ProcessDetails,
+    memory: &dyn MemoryBackend,
+    base_addr: usize,
+    address_type: &AddressType,
+) -> std::io::Result<usize> {
+    memory.resolve_from_base(&details.address_offsets[address_type], base_addr)
+}
+
+// Prints a matrix of the features supported by every known version, without needing a game
+pub fn print_supported_matrix(process_details: &[ProcessDetails]) {
+    let versions = process_details
+        .iter()
+        .map(|details| format!("{} {}", details.name, details.version.version))
+        .collect::<Vec<_>>();
+    let version_width = versions.iter().map(|v| v.len()).max().unwrap_or(0) + 2;
+
+    print!("{:<width$}", "", width = version_width);
+    for feature in Feature::all() {
+        print!("{:<width$}", feature.name(), width = FEATURE_COLUMN_WIDTH);
+    }
+    println!();
+
+    for (details, version) in process_details.iter().zip(versions) {
+        print!("{:<width$}", version, width = version_width);
+        for feature in Feature::all() {
+            let supported = if feature.missing_addresses(details).is_empty() { "yes" } else { "-" };
+            print!("{:<width$}", supported, width = FEATURE_COLUMN_WIDTH);
+        }
+        println!();
+    }
+}
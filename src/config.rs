@@ -1,9 +1,43 @@
 use livesplit_hotkey::KeyCode;
+use serde::de::{self, Deserializer};
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::path::PathBuf;
 use std::{env, fs};
 
-use crate::action::Action;
+use crate::action::{deserialize_slot, Action, FlyInput};
+
+// Rates and intervals end up as durations to wait between things, so anything that isn't a positive
+// number is rejected when the config is loaded
+fn deserialize_positive<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = f64::deserialize(deserializer)?;
+    if is_positive(value) {
+        Ok(value)
+    } else {
+        Err(de::Error::custom(format!("expected a positive number, got {}", value)))
+    }
+}
+
+fn deserialize_positive_rates<'de, D>(deserializer: D) -> Result<HashMap<String, f64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let rates = HashMap::<String, f64>::deserialize(deserializer)?;
+    match rates.iter().find(|(_, rate)| !is_positive(**rate)) {
+        Some((name, rate)) => Err(de::Error::custom(format!(
+            "expected a positive rate for {}, got {}",
+            name, rate
+        ))),
+        None => Ok(rates),
+    }
+}
+
+fn is_positive(value: f64) -> bool {
+    value.is_finite() && value > 0.0
+}
 
 fn default_config_path() -> PathBuf {
     env::current_exe()
@@ -35,6 +69,7 @@ fn default_hotkeys() -> Vec<Hotkey> {
         Hotkey::new(KeyCode::F6, Action::StorePosition {}),
         Hotkey::new(KeyCode::F7, Action::RestorePosition {}),
         Hotkey::new(KeyCode::F8, Action::ResetSkipCutsceneTracker {}),
+        Hotkey::new(KeyCode::F9, Action::ToggleFlyMode {}),
         Hotkey::new(KeyCode::Space, Action::SkipCutscene {}),
         Hotkey::new(KeyCode::W, Action::Forward { distance: 100.0 }),
         Hotkey::new(KeyCode::S, Action::Backward { distance: 100.0 }),
@@ -45,6 +80,39 @@ fn default_hotkeys() -> Vec<Hotkey> {
     ]
 }
 
+fn default_fly_hotkeys() -> Vec<FlyHotkey> {
+    vec![
+        FlyHotkey::new(KeyCode::W, FlyInput::Forward),
+        FlyHotkey::new(KeyCode::S, FlyInput::Backward),
+        FlyHotkey::new(KeyCode::A, FlyInput::Left),
+        FlyHotkey::new(KeyCode::D, FlyInput::Right),
+        FlyHotkey::new(KeyCode::Space, FlyInput::Up),
+        FlyHotkey::new(KeyCode::C, FlyInput::Down),
+        FlyHotkey::new(KeyCode::LShift, FlyInput::Fast),
+        FlyHotkey::new(KeyCode::LControl, FlyInput::Slow),
+    ]
+}
+
+fn default_fly_speed() -> f32 {
+    1000.0
+}
+
+fn default_fly_acceleration() -> f32 {
+    4000.0
+}
+
+fn default_fly_fast_multiplier() -> f32 {
+    4.0
+}
+
+fn default_fly_slow_multiplier() -> f32 {
+    0.25
+}
+
+fn default_fly_follow_camera_pitch() -> bool {
+    true
+}
+
 fn default_cutscene_blacklist_file() -> String {
     "https://gist.githubusercontent.com/Atorizil/734a7649471f0fa0a2a9f92a167e294b/raw/Blacklist.json".to_string()
 }
@@ -64,10 +132,74 @@ fn default_cutscene_timing_file_output() -> String {
         .into()
 }
 
+fn default_saved_positions_file() -> String {
+    env::current_exe()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .join("tomb-helper-positions.json")
+        .to_str()
+        .unwrap()
+        .into()
+}
+
+fn default_recording_directory() -> String {
+    env::current_exe()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .into()
+}
+
+fn default_recording_sample_rate() -> f64 {
+    60.0
+}
+
+fn default_ghost_report_interval() -> f64 {
+    1.0
+}
+
+fn default_ghost_search_window() -> f64 {
+    30.0
+}
+
+fn default_segment_history_file() -> String {
+    env::current_exe()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .join("tomb-helper-segments.json")
+        .to_str()
+        .unwrap()
+        .into()
+}
+
+fn default_tick_rate() -> f64 {
+    100.0
+}
+
+fn default_process_check_interval() -> f64 {
+    1.0
+}
+
+fn default_http_api_port() -> u16 {
+    16835
+}
+
+fn default_event_stream_port() -> u16 {
+    16836
+}
+
 fn default_livesplit_port() -> u32 {
     return 16834;
 }
 
+fn default_position_slots() -> u32 {
+    return 10;
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub struct Hotkey {
     pub key: KeyCode,
@@ -80,6 +212,229 @@ impl Hotkey {
     }
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct FlyHotkey {
+    pub key: KeyCode,
+    pub input: FlyInput,
+}
+
+impl FlyHotkey {
+    fn new(key: KeyCode, input: FlyInput) -> FlyHotkey {
+        FlyHotkey { key, input }
+    }
+}
+
+// Speeds are in game units per second, acceleration in game units per second squared
+#[derive(Debug, Clone, Deserialize)]
+pub struct FlyConfig {
+    #[serde(default = "default_fly_hotkeys")]
+    pub hotkeys: Vec<FlyHotkey>,
+    #[serde(default = "default_fly_speed")]
+    pub speed: f32,
+    #[serde(default = "default_fly_acceleration")]
+    pub acceleration: f32,
+    #[serde(default = "default_fly_fast_multiplier")]
+    pub fast_multiplier: f32,
+    #[serde(default = "default_fly_slow_multiplier")]
+    pub slow_multiplier: f32,
+    #[serde(default = "default_fly_follow_camera_pitch")]
+    pub follow_camera_pitch: bool,
+}
+
+impl Default for FlyConfig {
+    fn default() -> Self {
+        FlyConfig {
+            hotkeys: default_fly_hotkeys(),
+            speed: default_fly_speed(),
+            acceleration: default_fly_acceleration(),
+            fast_multiplier: default_fly_fast_multiplier(),
+            slow_multiplier: default_fly_slow_multiplier(),
+            follow_camera_pitch: default_fly_follow_camera_pitch(),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Deserialize)]
+pub enum RecordingFormat {
+    Csv,
+    JsonLines,
+}
+
+impl Default for RecordingFormat {
+    fn default() -> Self {
+        RecordingFormat::Csv
+    }
+}
+
+// Recordings are written to a new timestamped file in the directory each time, the sample rate is
+// in samples per second
+#[derive(Debug, Clone, Deserialize)]
+pub struct RecordingConfig {
+    #[serde(default = "default_recording_directory")]
+    pub directory: String,
+    #[serde(default)]
+    pub format: RecordingFormat,
+    #[serde(default = "default_recording_sample_rate", deserialize_with = "deserialize_positive")]
+    pub sample_rate: f64,
+}
+
+impl Default for RecordingConfig {
+    fn default() -> Self {
+        RecordingConfig {
+            directory: default_recording_directory(),
+            format: RecordingFormat::default(),
+            sample_rate: default_recording_sample_rate(),
+        }
+    }
+}
+
+// Times are in seconds
+#[derive(Debug, Clone, Deserialize)]
+pub struct GhostConfig {
+    #[serde(default)]
+    pub route_file: Option<String>,
+    #[serde(default = "default_ghost_report_interval", deserialize_with = "deserialize_positive")]
+    pub report_interval: f64,
+    #[serde(default = "default_ghost_search_window", deserialize_with = "deserialize_positive")]
+    pub search_window: f64,
+}
+
+impl Default for GhostConfig {
+    fn default() -> Self {
+        GhostConfig {
+            route_file: None,
+            report_interval: default_ghost_report_interval(),
+            search_window: default_ghost_search_window(),
+        }
+    }
+}
+
+// Coordinates are in the game's own units, as shown when storing a position
+#[derive(Debug, Clone, Deserialize)]
+pub enum ZoneShape {
+    Box { min: [f32; 3], max: [f32; 3] },
+    Sphere { center: [f32; 3], radius: f32 },
+}
+
+impl ZoneShape {
+    pub fn contains(&self, position: [f32; 3]) -> bool {
+        match self {
+            ZoneShape::Box { min, max } => (0..3).all(|i| min[i] <= position[i] && position[i] <= max[i]),
+            ZoneShape::Sphere { center, radius } => {
+                let distance_squared = (0..3).map(|i| (position[i] - center[i]).powi(2)).sum::<f32>();
+                distance_squared <= radius * radius
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TriggerZone {
+    pub name: String,
+    // Only used for the game with this name, or every game if not given
+    #[serde(default)]
+    pub game: Option<String>,
+    pub shape: ZoneShape,
+    #[serde(default)]
+    pub on_enter: Vec<Action>,
+    #[serde(default)]
+    pub on_exit: Vec<Action>,
+}
+
+// A segment runs from a stored position slot to a goal zone, optionally restarting if it takes
+// longer than the timeout in seconds
+#[derive(Debug, Clone, Deserialize)]
+pub struct SegmentConfig {
+    pub name: String,
+    #[serde(default)]
+    pub game: Option<String>,
+    #[serde(deserialize_with = "deserialize_slot")]
+    pub start_slot: String,
+    pub goal: ZoneShape,
+    #[serde(default)]
+    pub timeout: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum TelemetryOutput {
+    Off {},
+    Print {},
+    // Rewritten with the latest values as JSON on every update
+    File { path: String },
+}
+
+impl Default for TelemetryOutput {
+    fn default() -> Self {
+        TelemetryOutput::Off {}
+    }
+}
+
+// How often telemetry is reported is set by the telemetry handler's tick rate
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TelemetryConfig {
+    #[serde(default)]
+    pub output: TelemetryOutput,
+}
+
+// Rates are in ticks per second, handler_tick_rates is keyed by handler name (as shown by
+// PrintStatus) and overrides tick_rate, or the handler's own default rate, for that handler. The process check interval is in seconds.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SchedulerConfig {
+    #[serde(default = "default_tick_rate", deserialize_with = "deserialize_positive")]
+    pub tick_rate: f64,
+    #[serde(default, deserialize_with = "deserialize_positive_rates")]
+    pub handler_tick_rates: HashMap<String, f64>,
+    #[serde(default = "default_process_check_interval", deserialize_with = "deserialize_positive")]
+    pub process_check_interval: f64,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        SchedulerConfig {
+            tick_rate: default_tick_rate(),
+            handler_tick_rates: HashMap::new(),
+            process_check_interval: default_process_check_interval(),
+        }
+    }
+}
+
+// A file rewritten with the template whenever its placeholders' values change
+#[derive(Debug, Clone, Deserialize)]
+pub struct TextOutputConfig {
+    pub path: String,
+    pub template: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum HttpApi {
+    On {
+        #[serde(default = "default_http_api_port")]
+        port: u16,
+    },
+    Off {},
+}
+
+impl Default for HttpApi {
+    fn default() -> Self {
+        HttpApi::Off {}
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub enum EventStreamConfig {
+    On {
+        #[serde(default = "default_event_stream_port")]
+        port: u16,
+    },
+    Off {},
+}
+
+impl Default for EventStreamConfig {
+    fn default() -> Self {
+        EventStreamConfig::Off {}
+    }
+}
+
 #[derive(Debug, Clone, Deserialize)]
 pub enum CutsceneTiming {
     On {
@@ -109,4 +464,107 @@ pub struct Config {
     pub record_cutscene_timing: CutsceneTiming,
     #[serde(default)]
     pub force_version: Option<String>,
+    #[serde(default = "default_position_slots")]
+    pub position_slots: u32,
+    #[serde(default = "default_saved_positions_file")]
+    pub saved_positions_file: String,
+    #[serde(default)]
+    pub fly: FlyConfig,
+    #[serde(default)]
+    pub recording: RecordingConfig,
+    #[serde(default)]
+    pub ghost: GhostConfig,
+    #[serde(default)]
+    pub trigger_zones: Vec<TriggerZone>,
+    #[serde(default)]
+    pub segments: Vec<SegmentConfig>,
+    #[serde(default = "default_segment_history_file")]
+    pub segment_history_file: String,
+    #[serde(default)]
+    pub telemetry: TelemetryConfig,
+    #[serde(default)]
+    pub text_outputs: Vec<TextOutputConfig>,
+    #[serde(default)]
+    pub scheduler: SchedulerConfig,
+    #[serde(default)]
+    pub http_api: HttpApi,
+    #[serde(default)]
+    pub event_stream: EventStreamConfig,
+    #[serde(default)]
+    pub version_table_file: Option<String>,
+    #[serde(default)]
+    pub signature_scan_unknown_versions: bool,
+    #[serde(default)]
+    pub unknown_version_report_file: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn boxes_contain_their_inside_and_edges() {
+        let zone = ZoneShape::Box {
+            min: [-10.0, 0.0, 5.0],
+            max: [10.0, 20.0, 6.0],
+        };
+
+        assert!(zone.contains([0.0, 10.0, 5.5]));
+        assert!(zone.contains([-10.0, 0.0, 5.0]));
+        assert!(zone.contains([10.0, 20.0, 6.0]));
+        assert!(!zone.contains([10.5, 10.0, 5.5]));
+        assert!(!zone.contains([0.0, -0.5, 5.5]));
+        assert!(!zone.contains([0.0, 10.0, 6.5]));
+    }
+
+    #[test]
+    fn spheres_contain_everything_within_the_radius() {
+        let zone = ZoneShape::Sphere {
+            center: [100.0, 100.0, 0.0],
+            radius: 5.0,
+        };
+
+        assert!(zone.contains([100.0, 100.0, 0.0]));
+        assert!(zone.contains([103.0, 104.0, 0.0]));
+        assert!(zone.contains([100.0, 100.0, -5.0]));
+        assert!(!zone.contains([104.0, 104.0, 0.0]));
+        // Inside the bounding box but outside the sphere
+        assert!(!zone.contains([104.0, 104.0, 4.0]));
+    }
+
+    #[test]
+    fn zones_are_read_from_json() {
+        let zone: ZoneShape = serde_json::from_str(r#"{"Sphere": {"center": [1, 2, 3], "radius": 1.5}}"#).unwrap();
+        assert!(zone.contains([1.0, 2.0, 4.5]));
+        assert!(!zone.contains([1.0, 2.0, 4.6]));
+    }
+
+    #[test]
+    fn rates_must_be_positive() {
+        let ghost: GhostConfig = serde_json::from_str(r#"{"report_interval": 2.5}"#).unwrap();
+        assert_eq!(ghost.report_interval, 2.5);
+
+        for interval in &["0", "-10", "0.0"] {
+            let json = format!(r#"{{"report_interval": {}}}"#, interval);
+            assert!(serde_json::from_str::<GhostConfig>(&json).is_err(), "interval {} was accepted", interval);
+        }
+    }
+
+    #[test]
+    fn scheduler_rates_must_be_positive() {
+        let scheduler: SchedulerConfig =
+            serde_json::from_str(r#"{"tick_rate": 50, "handler_tick_rates": {"telemetry": 5}}"#).unwrap();
+        assert_eq!(scheduler.tick_rate, 50.0);
+        assert_eq!(scheduler.handler_tick_rates["telemetry"], 5.0);
+        assert_eq!(scheduler.process_check_interval, default_process_check_interval());
+
+        for json in &[
+            r#"{"tick_rate": 0}"#,
+            r#"{"tick_rate": -100}"#,
+            r#"{"handler_tick_rates": {"telemetry": 5, "position": 0}}"#,
+            r#"{"process_check_interval": 0}"#,
+        ] {
+            assert!(serde_json::from_str::<SchedulerConfig>(json).is_err(), "{} was accepted", json);
+        }
+    }
 }
@@ -1,13 +1,15 @@
 use crate::action::Action;
+use crate::event::Event;
 use crate::handler::Handler;
+use crate::memory_backend::MemoryBackend;
 use crate::process_details::{AddressOffsets, AddressType};
 use crate::cutscene_timing_info::{TimingInfo, TimingEntry};
 use crate::tracked_memory::TrackedMemory;
 use crate::readable_from_path::ReadableFromPath;
-use process_memory::{Architecture, ProcessHandle};
 use serde::Deserialize;
 use std::collections::HashMap;
 use std::error::Error;
+use std::rc::Rc;
 use std::fmt;
 use std::time::Instant;
 
@@ -18,7 +20,7 @@ pub struct CutsceneHandler {
     timeline: TrackedMemory<f32>,
     length: TrackedMemory<f32>,
     id: TrackedMemory<u32>,
-    handle: ProcessHandle,
+    memory: Rc<dyn MemoryBackend>,
     blacklist: HashMap<u32, BlacklistEntry>,
     timing_info: TimingInfo,
     total_time_skipped_rta: f32,
@@ -26,14 +28,15 @@ pub struct CutsceneHandler {
     skipping_cutscene: Option<TimingEntry>,
     skip_time: Option<f32>,
     fadeout_start: Option<Instant>,
+    current_id: Option<u32>,
+    events: Vec<Event>,
 }
 
 impl CutsceneHandler {
     pub fn new(
         address_offsets: &AddressOffsets,
-        arch: &Architecture,
         base_addr: &usize,
-        handle: &ProcessHandle,
+        memory: &Rc<dyn MemoryBackend>,
         blacklist_location: &String,
         timing_info_path: &String,
     ) -> Option<CutsceneHandler> {
@@ -43,34 +46,29 @@ impl CutsceneHandler {
             prompt: TrackedMemory::<u8>::new(
                 0,
                 address_offsets.get(&AddressType::CutscenePrompt)?.clone(),
-                *arch,
                 *base_addr,
             ),
             status: TrackedMemory::<u8>::new(
                 0,
                 address_offsets.get(&AddressType::CutsceneStatus)?.clone(),
-                *arch,
                 *base_addr,
             ),
             timeline: TrackedMemory::<f32>::new(
                 0.0,
                 address_offsets.get(&AddressType::CutsceneTimeline)?.clone(),
-                *arch,
                 *base_addr,
             ),
             length: TrackedMemory::<f32>::new(
                 0.0,
                 address_offsets.get(&AddressType::CutsceneLength)?.clone(),
-                *arch,
                 *base_addr,
             ),
             id: TrackedMemory::<u32>::new(
                 0,
                 address_offsets.get(&AddressType::CutsceneId)?.clone(),
-                *arch,
                 *base_addr,
             ),
-            handle: *handle,
+            memory: memory.clone(),
             blacklist: Blacklist::from_path(blacklist_location, &String::from("cutscene blacklist")),
             timing_info: TimingInfo::from_path(timing_info_path, &String::from("cutscene timing info")),
             total_time_skipped_rta: 0.0,
@@ -78,16 +76,37 @@ impl CutsceneHandler {
             skipping_cutscene: None,
             skip_time: None,
             fadeout_start: None,
+            current_id: None,
+            events: vec![Event::CutsceneTimeSaved { rta: 0.0, igt: 0.0 }],
         })
     }
 
+    fn time_saved_changed(&mut self) {
+        self.events.push(Event::CutsceneTimeSaved {
+            rta: self.total_time_skipped_rta,
+            igt: self.total_time_skipped_igt,
+        });
+    }
+
+    // Cutscene ids are 0 or unreadable outside of cutscenes
+    fn update_current_id(&mut self) {
+        let id = match self.id.fetch_from_game(&*self.memory) {
+            Ok(()) if self.id.data != 0 => Some(self.id.data),
+            _ => None,
+        };
+        if id != self.current_id {
+            self.current_id = id;
+            self.events.push(Event::CutsceneChanged { id });
+        }
+    }
+
     fn skip(&mut self) -> Result<(), Box<dyn Error>> {
         let valid_cutscene = || -> Result<(), Box<dyn Error>> {
-            self.prompt.fetch_from_game(self.handle)?;
-            self.status.fetch_from_game(self.handle)?;
-            self.timeline.fetch_from_game(self.handle)?;
-            self.length.fetch_from_game(self.handle)?;
-            self.id.fetch_from_game(self.handle)?;
+            self.prompt.fetch_from_game(&*self.memory)?;
+            self.status.fetch_from_game(&*self.memory)?;
+            self.timeline.fetch_from_game(&*self.memory)?;
+            self.length.fetch_from_game(&*self.memory)?;
+            self.id.fetch_from_game(&*self.memory)?;
             Ok(())
         }();
         if valid_cutscene.is_err() {
@@ -152,7 +171,7 @@ impl CutsceneHandler {
 
     fn start_fadeout(&mut self, cutscene_info: TimingEntry) -> Result<(), Box<dyn Error>> {
         self.status.data = 5;
-        if self.status.apply_to_game(self.handle).is_err() {
+        if self.status.apply_to_game(&*self.memory).is_err() {
             return Err(CutsceneError::new("Failed to set cutscene state!").into());
         }
 
@@ -166,11 +185,11 @@ impl CutsceneHandler {
 
     fn is_fadeout_finished(&mut self) -> bool {
         let valid_cutscene = || -> Result<(), Box<dyn Error>> {
-            self.prompt.fetch_from_game(self.handle)?;
-            self.status.fetch_from_game(self.handle)?;
-            self.timeline.fetch_from_game(self.handle)?;
-            self.length.fetch_from_game(self.handle)?;
-            self.id.fetch_from_game(self.handle)?;
+            self.prompt.fetch_from_game(&*self.memory)?;
+            self.status.fetch_from_game(&*self.memory)?;
+            self.timeline.fetch_from_game(&*self.memory)?;
+            self.length.fetch_from_game(&*self.memory)?;
+            self.id.fetch_from_game(&*self.memory)?;
             Ok(())
         }();
 
@@ -208,8 +227,17 @@ impl CutsceneHandler {
 
         println!("Skipped cutscene. Saved {} seconds RTA, {} seconds IGT.", time_skipped_rta, time_skipped_igt);
 
+        let mut ids = cutscene_info.ids.iter().cloned().collect::<Vec<_>>();
+        ids.sort();
+        self.events.push(Event::CutsceneSkipped {
+            ids,
+            rta_saved: time_skipped_rta,
+            igt_saved: time_skipped_igt,
+        });
+
         self.total_time_skipped_rta += time_skipped_rta;
         self.total_time_skipped_igt += time_skipped_igt;
+        self.time_saved_changed();
 
         self.skipping_cutscene = None;
         self.skip_time = None;
@@ -218,6 +246,39 @@ impl CutsceneHandler {
 }
 
 impl Handler for CutsceneHandler {
+    fn name(&self) -> &str {
+        "cutscene skipper"
+    }
+
+    fn status(&self) -> String {
+        let skipped = format!(
+            "skipped a total of {} seconds RTA, {} seconds IGT",
+            self.total_time_skipped_rta, self.total_time_skipped_igt
+        );
+        match &self.skipping_cutscene {
+            Some(_) => format!("skipping a cutscene, {}", skipped),
+            None => skipped,
+        }
+    }
+
+    // The totals are kept for when the game is restarted, but a skip in progress can't be timed
+    fn on_disconnect(&mut self) -> Result<(), Box<dyn Error>> {
+        self.skipping_cutscene = None;
+        self.skip_time = None;
+        self.fadeout_start = None;
+        println!("Skipped a total of {} seconds RTA, {} seconds IGT", self.total_time_skipped_rta, self.total_time_skipped_igt);
+        Ok(())
+    }
+
+    fn rebind(&mut self, base_addr: usize, memory: &Rc<dyn MemoryBackend>) {
+        self.prompt.rebind(base_addr);
+        self.status.rebind(base_addr);
+        self.timeline.rebind(base_addr);
+        self.length.rebind(base_addr);
+        self.id.rebind(base_addr);
+        self.memory = memory.clone();
+    }
+
     fn handle_tick(&mut self) -> Result<(), Box<dyn Error>> {
         if self.skipping_cutscene.is_some() {
             if self.is_fadeout_finished() {
@@ -225,6 +286,7 @@ impl Handler for CutsceneHandler {
             }
 
         }
+        self.update_current_id();
 
         Ok(())
     }
@@ -235,12 +297,17 @@ impl Handler for CutsceneHandler {
                 println!("Skipped a total of {} seconds RTA, {} seconds IGT", self.total_time_skipped_rta, self.total_time_skipped_igt);
                 self.total_time_skipped_rta = 0.0;
                 self.total_time_skipped_igt = 0.0;
+                self.time_saved_changed();
                 println!("Reset skip cutscene tracker");
                 Ok(())
             }
             _ => Ok(()),
         }
     }
+
+    fn take_events(&mut self) -> Vec<Event> {
+        self.events.drain(..).collect()
+    }
 }
 
 #[derive(Debug)]
@@ -273,3 +340,121 @@ impl ReadableFromPath for Blacklist {}
 pub struct BlacklistEntry {
     pub skip_delay: f32,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_backend::InMemoryBackend;
+    use process_memory::Architecture;
+
+    const PROMPT: usize = 0x100;
+    const STATUS: usize = 0x200;
+    const TIMELINE: usize = 0x300;
+    const LENGTH: usize = 0x400;
+    const ID: usize = 0x500;
+
+    fn cutscene_handler(backend: &Rc<InMemoryBackend>) -> CutsceneHandler {
+        let address_offsets: AddressOffsets = vec![
+            (AddressType::CutscenePrompt, vec![PROMPT]),
+            (AddressType::CutsceneStatus, vec![STATUS]),
+            (AddressType::CutsceneTimeline, vec![TIMELINE]),
+            (AddressType::CutsceneLength, vec![LENGTH]),
+            (AddressType::CutsceneId, vec![ID]),
+        ]
+        .into_iter()
+        .collect();
+        let memory: Rc<dyn MemoryBackend> = backend.clone();
+        let mut handler = CutsceneHandler::new(&address_offsets, &0, &memory, &String::new(), &String::new()).unwrap();
+        handler.timing_info = TimingInfo {
+            cutscenes: vec![TimingEntry {
+                ids: vec![42].into_iter().collect(),
+                real_time: 30.0,
+                in_game_time: 30.0,
+                skippable_at_real_time: None,
+                skippable_at_in_game_time: None,
+            }],
+        };
+        handler
+    }
+
+    fn play_cutscene(backend: &InMemoryBackend, prompt: u8, timeline: f32) {
+        backend.set(PROMPT, prompt);
+        backend.set(STATUS, 0_u8);
+        backend.set(TIMELINE, timeline);
+        backend.set(LENGTH, 60.0_f32);
+        backend.set(ID, 42_u32);
+    }
+
+    fn skip_cutscene(handler: &mut CutsceneHandler, backend: &InMemoryBackend, timeline: f32) {
+        play_cutscene(backend, 1, timeline);
+        handler.handle_action(Action::SkipCutscene {}).unwrap();
+        assert_eq!(backend.get::<u8>(STATUS).unwrap(), 5);
+
+        // The fadeout finishes once the game has moved on from the cutscene
+        backend.set(ID, 0_u32);
+        handler.handle_tick().unwrap();
+        assert!(handler.skipping_cutscene.is_none());
+    }
+
+    #[test]
+    fn skips_accumulate_the_time_saved() {
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let mut handler = cutscene_handler(&backend);
+
+        skip_cutscene(&mut handler, &backend, 10.0);
+        assert!((handler.total_time_skipped_rta - 20.0).abs() < 0.1);
+        assert!((handler.total_time_skipped_igt - 20.0).abs() < 0.1);
+
+        skip_cutscene(&mut handler, &backend, 20.0);
+        assert!((handler.total_time_skipped_rta - 30.0).abs() < 0.1);
+        assert!((handler.total_time_skipped_igt - 30.0).abs() < 0.1);
+
+        let skips = handler
+            .take_events()
+            .into_iter()
+            .filter(|event| matches!(event, Event::CutsceneSkipped { .. }))
+            .count();
+        assert_eq!(skips, 2);
+    }
+
+    #[test]
+    fn resetting_the_tracker_clears_the_totals() {
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let mut handler = cutscene_handler(&backend);
+        skip_cutscene(&mut handler, &backend, 10.0);
+        handler.take_events();
+
+        handler.handle_action(Action::ResetSkipCutsceneTracker {}).unwrap();
+        assert_eq!(handler.total_time_skipped_rta, 0.0);
+        assert_eq!(handler.total_time_skipped_igt, 0.0);
+        assert!(matches!(
+            handler.take_events().as_slice(),
+            [Event::CutsceneTimeSaved { rta, igt }] if *rta == 0.0 && *igt == 0.0
+        ));
+    }
+
+    #[test]
+    fn does_not_skip_too_early_or_without_a_prompt() {
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let mut handler = cutscene_handler(&backend);
+
+        play_cutscene(&backend, 1, 3.0);
+        assert!(handler.handle_action(Action::SkipCutscene {}).is_err());
+        play_cutscene(&backend, 0, 10.0);
+        assert!(handler.handle_action(Action::SkipCutscene {}).is_err());
+
+        assert_eq!(backend.get::<u8>(STATUS).unwrap(), 0);
+        assert!(handler.skipping_cutscene.is_none());
+    }
+
+    #[test]
+    fn does_nothing_outside_of_cutscenes() {
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let mut handler = cutscene_handler(&backend);
+
+        handler.handle_action(Action::SkipCutscene {}).unwrap();
+        handler.handle_tick().unwrap();
+        assert_eq!(handler.total_time_skipped_rta, 0.0);
+        assert!(backend.get::<u8>(STATUS).is_err());
+    }
+}
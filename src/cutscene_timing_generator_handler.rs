@@ -1,11 +1,12 @@
 use crate::action::Action;
 use crate::handler::Handler;
+use crate::memory_backend::MemoryBackend;
 use crate::process_details::{AddressOffsets, AddressType};
 use crate::tracked_memory::TrackedMemory;
 use crate::cutscene_timing_info::{TimingInfo, TimingEntry};
 use crate::readable_from_path::ReadableFromPath;
-use process_memory::{Architecture, ProcessHandle};
 use std::error::Error;
+use std::rc::Rc;
 use std::time::{Duration, Instant};
 use std::net::TcpStream;
 use std::io::{BufRead, BufReader, Write};
@@ -17,7 +18,7 @@ use std::{fmt, iter};
 pub struct CutsceneTimingGeneratorHandler {
     timeline: TrackedMemory<f32>,
     id: TrackedMemory<u32>,
-    handle: ProcessHandle,
+    memory: Rc<dyn MemoryBackend>,
     current_start_game_time: Option<std::time::Duration>,
     current_start_real_time: Option<Instant>,
     current_timeline: Option<f32>,
@@ -33,9 +34,8 @@ pub struct CutsceneTimingGeneratorHandler {
 impl CutsceneTimingGeneratorHandler {
     pub fn new(
         address_offsets: &AddressOffsets,
-        arch: &Architecture,
         base_addr: &usize,
-        handle: &ProcessHandle,
+        memory: &Rc<dyn MemoryBackend>,
         timing_info_path: &String,
         livesplit_port: &u32,
     ) -> Option<CutsceneTimingGeneratorHandler> {
@@ -45,16 +45,14 @@ impl CutsceneTimingGeneratorHandler {
             timeline: TrackedMemory::<f32>::new(
                 0.0,
                 address_offsets.get(&AddressType::CutsceneTimeline)?.clone(),
-                *arch,
                 *base_addr,
             ),
             id: TrackedMemory::<u32>::new(
                 0,
                 address_offsets.get(&AddressType::CutsceneId)?.clone(),
-                *arch,
                 *base_addr,
             ),
-            handle: *handle,
+            memory: memory.clone(),
             current_start_game_time: None,
             current_start_real_time: None,
             current_timeline: None,
@@ -69,7 +67,6 @@ impl CutsceneTimingGeneratorHandler {
             prompt: TrackedMemory::<u8>::new(
                 0,
                 address_offsets.get(&AddressType::CutscenePrompt)?.clone(),
-                *arch,
                 *base_addr,
             ),
         })
@@ -172,8 +169,8 @@ impl CutsceneTimingGeneratorHandler {
     fn update_cutscene_tracker(&mut self) -> Result<(), Box<dyn Error>> {
         let was_in_valid_cutscene = self.current_timeline.is_some();
         let mut now_in_valid_cutscene = || -> Result<(), Box<dyn Error>> {
-            self.timeline.fetch_from_game(self.handle)?;
-            self.id.fetch_from_game(self.handle)?;
+            self.timeline.fetch_from_game(&*self.memory)?;
+            self.id.fetch_from_game(&*self.memory)?;
             Ok(())
         }().is_ok();
         now_in_valid_cutscene = now_in_valid_cutscene && self.id.data != 0;
@@ -201,7 +198,7 @@ impl CutsceneTimingGeneratorHandler {
             self.current_timeline = Some(self.timeline.data);
 
             if self.skip_game_time.is_none() && self.skip_real_time.is_none() {
-                self.prompt.fetch_from_game(self.handle)?;
+                self.prompt.fetch_from_game(&*self.memory)?;
                 if self.prompt.data == 2 {
                     self.skip_game_time = Some(self.get_livesplit_time()?);
                     self.skip_real_time = Some(Instant::now());
@@ -222,6 +219,36 @@ impl CutsceneTimingGeneratorHandler {
 }
 
 impl Handler for CutsceneTimingGeneratorHandler {
+    fn name(&self) -> &str {
+        "cutscene timing generator"
+    }
+
+    fn status(&self) -> String {
+        let timed = format!("{} cutscenes timed", self.timing_info.cutscenes.len());
+        match self.current_timeline {
+            Some(_) => format!("timing a cutscene, {}", timed),
+            None => timed,
+        }
+    }
+
+    // A cutscene being timed when the game exits can't be finished
+    fn on_disconnect(&mut self) -> Result<(), Box<dyn Error>> {
+        self.current_start_game_time = None;
+        self.current_start_real_time = None;
+        self.current_timeline = None;
+        self.current_id_list.clear();
+        self.skip_game_time = None;
+        self.skip_real_time = None;
+        Ok(())
+    }
+
+    fn rebind(&mut self, base_addr: usize, memory: &Rc<dyn MemoryBackend>) {
+        self.timeline.rebind(base_addr);
+        self.id.rebind(base_addr);
+        self.prompt.rebind(base_addr);
+        self.memory = memory.clone();
+    }
+
     fn handle_tick(&mut self) -> Result<(), Box<dyn Error>> {
         self.update_cutscene_tracker()?;
         Ok(())
@@ -0,0 +1,26 @@
+use std::error::Error;
+use std::fmt;
+
+// An error from one part of tomb-helper, shown as e.g. "Position Error: No position stored in slot 1"
+#[derive(Debug)]
+pub struct HelperError {
+    context: &'static str,
+    message: String,
+}
+
+impl HelperError {
+    pub fn new(context: &'static str, message: impl Into<String>) -> HelperError {
+        HelperError {
+            context,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for HelperError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} Error: {}", self.context, self.message)
+    }
+}
+
+impl Error for HelperError {}
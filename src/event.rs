@@ -0,0 +1,191 @@
+use crate::action::PositionKind;
+use crate::saved_positions::SavedSlots;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+// Handlers and event stream clients subscribe to events by category
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventCategory {
+    Connection,
+    Cutscene,
+    Position,
+    Ghost,
+    Telemetry,
+}
+
+impl EventCategory {
+    pub fn all() -> Vec<EventCategory> {
+        vec![
+            EventCategory::Connection,
+            EventCategory::Cutscene,
+            EventCategory::Position,
+            EventCategory::Ghost,
+            EventCategory::Telemetry,
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventCategory::Connection => "connection",
+            EventCategory::Cutscene => "cutscene",
+            EventCategory::Position => "position",
+            EventCategory::Ghost => "ghost",
+            EventCategory::Telemetry => "telemetry",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<EventCategory> {
+        EventCategory::all().into_iter().find(|category| category.name() == name)
+    }
+}
+
+// Things that happen in one handler that the rest of tomb-helper may want to react to
+#[derive(Debug, Clone, Serialize)]
+pub enum Event {
+    Connected {
+        game: String,
+        version: String,
+    },
+    Disconnected {
+        game: String,
+        version: String,
+    },
+    // Positive values are ahead of the reference route, negative behind
+    GhostComparison {
+        elapsed: f64,
+        time_ahead: f64,
+        distance_ahead: f32,
+        distance_from_route: f32,
+    },
+    SavedSlotsChanged {
+        handler: PositionKind,
+        slots: SavedSlots,
+    },
+    PositionStored {
+        handler: PositionKind,
+        slot: String,
+        position: [f32; 3],
+    },
+    PositionRestored {
+        handler: PositionKind,
+        slot: String,
+        position: [f32; 3],
+    },
+    // Any jump in position from restoring, undoing or redoing
+    Teleported {
+        handler: PositionKind,
+        from: [f32; 3],
+        to: [f32; 3],
+    },
+    // The stored position that a position handler would restore, if there is one
+    SelectedPositionChanged {
+        handler: PositionKind,
+        position: Option<[f32; 3]>,
+    },
+    // Totals since the skip cutscene tracker was last reset, in seconds
+    CutsceneTimeSaved {
+        rta: f32,
+        igt: f32,
+    },
+    // None when not in a cutscene
+    CutsceneChanged {
+        id: Option<u32>,
+    },
+    // The time saved by this skip, in seconds
+    CutsceneSkipped {
+        ids: Vec<u32>,
+        rta_saved: f32,
+        igt_saved: f32,
+    },
+    // Speeds are in units per second, with vertical speed positive going up
+    Telemetry {
+        position: [f32; 3],
+        horizontal_speed: f32,
+        vertical_speed: f32,
+        distance_from_saved: Option<f32>,
+    },
+}
+
+impl Event {
+    pub fn category(&self) -> EventCategory {
+        match self {
+            Event::Connected { .. } | Event::Disconnected { .. } => EventCategory::Connection,
+            Event::GhostComparison { .. } => EventCategory::Ghost,
+            Event::SavedSlotsChanged { .. }
+            | Event::PositionStored { .. }
+            | Event::PositionRestored { .. }
+            | Event::Teleported { .. }
+            | Event::SelectedPositionChanged { .. } => EventCategory::Position,
+            Event::CutsceneTimeSaved { .. } | Event::CutsceneChanged { .. } | Event::CutsceneSkipped { .. } => {
+                EventCategory::Cutscene
+            }
+            Event::Telemetry { .. } => EventCategory::Telemetry,
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::Connected { game, version } => write!(f, "Connected to {} {}", game, version),
+            Event::Disconnected { game, version } => write!(f, "Disconnected from {} {}", game, version),
+            Event::GhostComparison {
+                elapsed,
+                time_ahead,
+                distance_ahead,
+                distance_from_route,
+            } => write!(
+                f,
+                "Ghost at {:.1}s: {:+.2}s, {:+.0} units along the route, {:.0} units off it",
+                elapsed, time_ahead, distance_ahead, distance_from_route
+            ),
+            Event::SavedSlotsChanged { handler, slots } => {
+                write!(f, "{} {} slots stored", slots.len(), handler)
+            }
+            Event::PositionStored { handler, slot, position: [x, y, z] } => {
+                write!(f, "Stored {} in slot {} ({}, {}, {})", handler, slot, x, y, z)
+            }
+            Event::PositionRestored { handler, slot, position: [x, y, z] } => {
+                write!(f, "Restored {} from slot {} ({}, {}, {})", handler, slot, x, y, z)
+            }
+            Event::Teleported { handler, from, to } => write!(
+                f,
+                "Teleported {} from ({}, {}, {}) to ({}, {}, {})",
+                handler, from[0], from[1], from[2], to[0], to[1], to[2]
+            ),
+            Event::SelectedPositionChanged { handler, position } => match position {
+                Some([x, y, z]) => write!(f, "Selected {} ({}, {}, {})", handler, x, y, z),
+                None => write!(f, "Selected {} slot is empty", handler),
+            },
+            Event::CutsceneTimeSaved { rta, igt } => {
+                write!(f, "Saved {:.2} seconds RTA, {:.2} seconds IGT skipping cutscenes", rta, igt)
+            }
+            Event::CutsceneChanged { id } => match id {
+                Some(id) => write!(f, "In cutscene {}", id),
+                None => write!(f, "Not in a cutscene"),
+            },
+            Event::CutsceneSkipped { ids, rta_saved, igt_saved } => write!(
+                f,
+                "Skipped cutscene {:?}, saving {:.2} seconds RTA, {:.2} seconds IGT",
+                ids, rta_saved, igt_saved
+            ),
+            Event::Telemetry {
+                position: [x, y, z],
+                horizontal_speed,
+                vertical_speed,
+                distance_from_saved,
+            } => {
+                write!(
+                    f,
+                    "Position ({:.0}, {:.0}, {:.0}), speed {:.0} horizontal {:+.0} vertical",
+                    x, y, z, horizontal_speed, vertical_speed
+                )?;
+                match distance_from_saved {
+                    Some(distance) => write!(f, ", {:.0} from saved position", distance),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
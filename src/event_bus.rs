@@ -0,0 +1,193 @@
+use crate::event::Event;
+use crate::handler::Handler;
+
+// Somewhere outside of the handlers that events are sent to, e.g. the event stream
+pub trait EventSink {
+    fn publish(&self, event: &Event);
+}
+
+// Collects the events raised by handlers (and by connect itself) and delivers each one to the
+// handlers subscribed to its category and to every sink. Events raised while handling another
+// event are delivered on the next route.
+#[derive(Default)]
+pub struct EventBus {
+    pending: Vec<Event>,
+    sinks: Vec<Box<dyn EventSink>>,
+}
+
+impl EventBus {
+    pub fn new() -> EventBus {
+        Default::default()
+    }
+
+    pub fn add_sink(&mut self, sink: Box<dyn EventSink>) {
+        self.sinks.push(sink);
+    }
+
+    pub fn emit(&mut self, event: Event) {
+        self.pending.push(event);
+    }
+
+    pub fn route(&mut self, handlers: &mut [Box<dyn Handler>]) {
+        for handler in handlers.iter_mut() {
+            self.pending.extend(handler.take_events());
+        }
+
+        for event in self.pending.drain(..) {
+            for handler in handlers.iter_mut() {
+                if !handler.subscriptions().contains(&event.category()) {
+                    continue;
+                }
+                handler
+                    .handle_event(&event)
+                    .unwrap_or_else(|msg| eprintln!("Error: {}: {}", handler.name(), msg));
+            }
+            for sink in &self.sinks {
+                sink.publish(&event);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::Action;
+    use crate::event::EventCategory;
+    use crate::memory_backend::MemoryBackend;
+    use std::cell::RefCell;
+    use std::error::Error;
+    use std::rc::Rc;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    // Raises the given events on the next route, plus any reactions to the events it receives
+    struct TestHandler {
+        subscriptions: Vec<EventCategory>,
+        raised: Vec<Event>,
+        reaction: Option<Event>,
+        received: Log,
+    }
+
+    impl TestHandler {
+        fn new(subscriptions: Vec<EventCategory>, raised: Vec<Event>) -> (TestHandler, Log) {
+            let received = Log::default();
+            let handler = TestHandler {
+                subscriptions,
+                raised,
+                reaction: None,
+                received: received.clone(),
+            };
+            (handler, received)
+        }
+    }
+
+    impl Handler for TestHandler {
+        fn name(&self) -> &str {
+            "test"
+        }
+
+        fn status(&self) -> String {
+            String::new()
+        }
+
+        fn handle_tick(&mut self) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+
+        fn handle_action(&mut self, _action: Action) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+
+        fn rebind(&mut self, _base_addr: usize, _memory: &Rc<dyn MemoryBackend>) {}
+
+        fn take_events(&mut self) -> Vec<Event> {
+            self.raised.drain(..).collect()
+        }
+
+        fn subscriptions(&self) -> Vec<EventCategory> {
+            self.subscriptions.clone()
+        }
+
+        fn handle_event(&mut self, event: &Event) -> Result<(), Box<dyn Error>> {
+            self.received.borrow_mut().push(event.to_string());
+            self.raised.extend(self.reaction.take());
+            Ok(())
+        }
+    }
+
+    struct TestSink {
+        published: Log,
+    }
+
+    impl EventSink for TestSink {
+        fn publish(&self, event: &Event) {
+            self.published.borrow_mut().push(event.to_string());
+        }
+    }
+
+    fn ghost_comparison() -> Event {
+        Event::GhostComparison {
+            elapsed: 10.0,
+            time_ahead: 1.5,
+            distance_ahead: 20.0,
+            distance_from_route: 2.0,
+        }
+    }
+
+    fn cutscene_changed() -> Event {
+        Event::CutsceneChanged { id: Some(7) }
+    }
+
+    fn connected() -> Event {
+        Event::Connected {
+            game: "Tomb Raider".to_string(),
+            version: "1.0".to_string(),
+        }
+    }
+
+    fn names(events: &[Event]) -> Vec<String> {
+        events.iter().map(|event| event.to_string()).collect()
+    }
+
+    #[test]
+    fn delivers_events_to_subscribers_and_sinks() {
+        let (raiser, raiser_received) = TestHandler::new(vec![], vec![ghost_comparison(), cutscene_changed()]);
+        let (ghost, ghost_received) = TestHandler::new(vec![EventCategory::Ghost], vec![]);
+        let (everything, everything_received) = TestHandler::new(EventCategory::all(), vec![]);
+        let mut handlers: Vec<Box<dyn Handler>> = vec![Box::new(raiser), Box::new(ghost), Box::new(everything)];
+        let published = Log::default();
+        let mut bus = EventBus::new();
+        bus.add_sink(Box::new(TestSink {
+            published: published.clone(),
+        }));
+
+        bus.emit(connected());
+        bus.route(&mut handlers);
+
+        assert!(raiser_received.borrow().is_empty());
+        assert_eq!(*ghost_received.borrow(), names(&[ghost_comparison()]));
+        let all = names(&[connected(), ghost_comparison(), cutscene_changed()]);
+        assert_eq!(*everything_received.borrow(), all);
+        assert_eq!(*published.borrow(), all);
+
+        // Nothing is delivered twice
+        bus.route(&mut handlers);
+        assert_eq!(*published.borrow(), all);
+    }
+
+    #[test]
+    fn delivers_reactions_on_the_next_route() {
+        let (mut reacting, _) = TestHandler::new(vec![EventCategory::Ghost], vec![ghost_comparison()]);
+        reacting.reaction = Some(cutscene_changed());
+        let (cutscene, cutscene_received) = TestHandler::new(vec![EventCategory::Cutscene], vec![]);
+        let mut handlers: Vec<Box<dyn Handler>> = vec![Box::new(reacting), Box::new(cutscene)];
+        let mut bus = EventBus::new();
+
+        bus.route(&mut handlers);
+        assert!(cutscene_received.borrow().is_empty());
+
+        bus.route(&mut handlers);
+        assert_eq!(*cutscene_received.borrow(), names(&[cutscene_changed()]));
+    }
+}
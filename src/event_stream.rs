@@ -0,0 +1,110 @@
+use crate::event::{Event, EventCategory};
+use crate::event_bus::EventSink;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashSet;
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+use tungstenite::handshake::server::{Request, Response};
+use tungstenite::{accept_hdr, Message};
+
+// How long a client's thread waits for messages from it before sending on any new events
+const CLIENT_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// Each client is sent the category and JSON of every event
+type Client = Sender<(EventCategory, String)>;
+
+// Sent by clients to change which categories of event they receive
+#[derive(Debug, Deserialize)]
+struct Subscription {
+    subscribe: Vec<EventCategory>,
+}
+
+// Pushes events to WebSocket clients on localhost as JSON. This lives for the whole run of
+// tomb-helper rather than one connection to the game so that clients see connects and disconnects.
+#[derive(Debug, Clone)]
+pub struct EventStream {
+    clients: Arc<Mutex<Vec<Client>>>,
+}
+
+impl EventStream {
+    pub fn start(port: u16) -> io::Result<EventStream> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        println!("Serving event stream on ws://127.0.0.1:{}/", port);
+
+        let event_stream = EventStream {
+            clients: Arc::new(Mutex::new(vec![])),
+        };
+        let clients = event_stream.clients.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().filter_map(Result::ok) {
+                let (sender, receiver) = mpsc::channel();
+                clients.lock().unwrap().push(sender);
+                thread::spawn(move || serve_client(stream, receiver));
+            }
+        });
+        Ok(event_stream)
+    }
+}
+
+impl EventSink for EventStream {
+    fn publish(&self, event: &Event) {
+        let message = json!({"category": event.category(), "event": event}).to_string();
+        // Clients that have gone away are dropped the next time they would be sent an event
+        self.clients
+            .lock()
+            .unwrap()
+            .retain(|client| client.send((event.category(), message.clone())).is_ok());
+    }
+}
+
+// Clients get every category unless they ask for specific ones, either in the query string when
+// connecting (e.g. ws://127.0.0.1:16836/?categories=cutscene,position) or by sending a
+// subscription such as {"subscribe": ["cutscene"]}
+fn serve_client(stream: TcpStream, events: Receiver<(EventCategory, String)>) {
+    let mut categories: Option<HashSet<EventCategory>> = None;
+    let read_categories = |request: &Request, response: Response| {
+        categories = request.uri().query().and_then(|query| {
+            query
+                .split('&')
+                .find_map(|pair| pair.strip_prefix("categories="))
+                .map(|list| list.split(',').filter_map(EventCategory::from_name).collect())
+        });
+        Ok(response)
+    };
+    let mut socket = match accept_hdr(stream, read_categories) {
+        Ok(socket) => socket,
+        Err(msg) => {
+            eprintln!("Error: Event stream handshake failed: {}", msg);
+            return;
+        }
+    };
+    if socket.get_ref().set_read_timeout(Some(CLIENT_POLL_INTERVAL)).is_err() {
+        return;
+    }
+
+    loop {
+        match socket.read() {
+            Ok(Message::Text(text)) => match serde_json::from_str::<Subscription>(&text) {
+                Ok(subscription) => categories = Some(subscription.subscribe.into_iter().collect()),
+                Err(msg) => eprintln!("Error: Invalid event stream subscription {:?}: {}", text, msg),
+            },
+            Ok(Message::Close(_)) => return,
+            Ok(_) => {}
+            Err(tungstenite::Error::Io(e))
+                if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut => {}
+            Err(_) => return,
+        }
+
+        for (category, message) in events.try_iter() {
+            let subscribed = categories.as_ref().map_or(true, |categories| categories.contains(&category));
+            if subscribed && socket.send(Message::Text(message)).is_err() {
+                return;
+            }
+        }
+    }
+}
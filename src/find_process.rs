@@ -1,16 +1,29 @@
+use crate::memory_backend::{MemoryBackend, ProcessBackend};
 use crate::process_details::{AddressType, ProcessDetails};
-use process_memory::{CopyAddress, DataMember, Memory, Pid, TryIntoProcessHandle};
-use std::ptr::{null, null_mut};
+use crate::version_fingerprint::VersionFingerprint;
+use crate::version_report::UNKNOWN_VERSION_GRACE_PERIOD;
+use process_memory::{Pid, TryIntoProcessHandle};
+use std::collections::HashMap;
+use std::ptr::null;
+use std::rc::Rc;
+use std::time::Instant;
+#[cfg(windows)]
+use std::ptr::null_mut;
+#[cfg(windows)]
 use winapi::shared::winerror::WAIT_TIMEOUT;
+#[cfg(windows)]
 use winapi::um::winnt::SYNCHRONIZE;
+#[cfg(target_os = "linux")]
+use std::fs;
 
 pub fn find_process(
     possible_processes: Vec<ProcessDetails>,
     force_version: Option<String>,
-) -> Option<(Pid, process_memory::ProcessHandle, usize, ProcessDetails)> {
+) -> Option<(Pid, Rc<dyn MemoryBackend>, usize, ProcessDetails)> {
     possible_processes.iter().find_map(|details| {
         let pid = get_pid(&details.executable_name)?;
         let handle = pid.try_into_process_handle().ok()?;
+        let memory: Rc<dyn MemoryBackend> = Rc::new(ProcessBackend::new(handle, details.arch));
         let base_addr = get_base_address(pid) as *const _ as usize;
 
         if force_version == Some(details.version.version.clone()) {
@@ -18,21 +31,21 @@ pub fn find_process(
                 "Warning: Forcing version to {}, some functions may not work as expected!",
                 details.version.version
             );
-            return Some((pid, handle, base_addr, details.clone()));
+            return Some((pid, memory, base_addr, details.clone()));
         }
 
-        // Try using the image size first, then the version string
-        if let Some(image_size) = details.version.image_size {
-            let a = get_image_size(handle, base_addr).ok()?;
-            if image_size != a {
+        // Try using the PE fingerprint first, then the version string
+        if details.version.has_fingerprint() {
+            let fingerprint = VersionFingerprint::read(&*memory, base_addr).ok()?;
+            if !details.version.matches_fingerprint(&fingerprint) {
                 return None;
             }
 
-            return Some((pid, handle, base_addr, details.clone()));
+            return Some((pid, memory, base_addr, details.clone()));
         }
 
         let version_in_memory = try_read_std_string_utf8(
-            handle,
+            &*memory,
             details.address_offsets.get(&AddressType::Version)?.clone(),
             base_addr,
             details.version.version.len(),
@@ -42,11 +55,51 @@ pub fn find_process(
         if version_in_memory != details.version.version {
             None
         } else {
-            Some((pid, handle, base_addr, details.clone()))
+            Some((pid, memory.clone(), base_addr, details.clone()))
         }
     })
 }
 
+// Falls back to resolving every address by signature for games that find_process doesn't recognise.
+// Known versions can't be identified until the game has started up, so like the unknown version
+// report this waits until a process has gone unrecognised for the grace period.
+#[derive(Debug, Default)]
+pub struct SignatureFallback {
+    first_seen: HashMap<Pid, Instant>,
+}
+
+impl SignatureFallback {
+    // Uses the first entry with signatures for a running game, dropping its fixed offsets since
+    // they belong to a different version
+    pub fn find_process(
+        &mut self,
+        possible_processes: &[ProcessDetails],
+    ) -> Option<(Pid, Rc<dyn MemoryBackend>, usize, ProcessDetails)> {
+        possible_processes
+            .iter()
+            .filter(|details| !details.signatures.is_empty())
+            .find_map(|details| {
+                let pid = get_pid(&details.executable_name)?;
+                let first_seen = *self.first_seen.entry(pid).or_insert_with(Instant::now);
+                if first_seen.elapsed() < UNKNOWN_VERSION_GRACE_PERIOD {
+                    return None;
+                }
+                let handle = pid.try_into_process_handle().ok()?;
+                let memory: Rc<dyn MemoryBackend> = Rc::new(ProcessBackend::new(handle, details.arch));
+                let base_addr = get_base_address(pid) as *const _ as usize;
+
+                println!(
+                    "Warning: Unknown version of {}, using signatures from {}, some functions may not work as expected!",
+                    details.name, details.version.version
+                );
+                let mut details = details.clone();
+                details.version.version = format!("unknown version (signatures from {})", details.version.version);
+                details.address_offsets.clear();
+                Some((pid, memory, base_addr, details))
+            })
+    }
+}
+
 #[cfg(windows)]
 pub fn get_base_address(pid: Pid) -> *const u8 {
     let mut module = winapi::um::tlhelp32::MODULEENTRY32 {
@@ -139,47 +192,124 @@ pub fn is_process_running(pid: Pid) -> bool {
 }
 
 pub fn get_image_size(
-    handle: process_memory::ProcessHandle,
+    memory: &dyn MemoryBackend,
     base_addr: usize,
 ) -> Result<usize, std::io::Error> {
     let mut image_optional_header_offset_bytes = [0_u8; 4];
-    handle.copy_address(base_addr + 0x3C, &mut image_optional_header_offset_bytes)?;
+    memory.read_bytes(base_addr + 0x3C, &mut image_optional_header_offset_bytes)?;
     let image_optional_header_offset = u32::from_le_bytes(image_optional_header_offset_bytes) as usize;
 
     let mut image_size_bytes = [0_u8; 4];
-    handle.copy_address(base_addr + image_optional_header_offset + 0x50, &mut image_size_bytes)?;
+    memory.read_bytes(base_addr + image_optional_header_offset + 0x50, &mut image_size_bytes)?;
     let image_size = u32::from_le_bytes(image_size_bytes) as usize;
 
     Ok(image_size)
 }
 
 pub fn try_read_std_string_utf8(
-    handle: process_memory::ProcessHandle,
+    memory: &dyn MemoryBackend,
     offsets: Vec<usize>,
     base_addr: usize,
     length: usize,
 ) -> Result<String, std::io::Error> {
-    let mut offsets_with_base = offsets.clone();
-    offsets_with_base[0] += base_addr;
-
-    let offset = DataMember::<u8>::new_offset(handle, offsets_with_base);
+    let addr = memory.resolve_from_base(&offsets, base_addr)?;
     let mut bytes = vec![0_u8; length];
-    handle.copy_address(offset.get_offset()?, &mut bytes)?;
+    memory.read_bytes(addr, &mut bytes)?;
 
     String::from_utf8(bytes).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
 }
 
-#[cfg(not(windows))]
+// Under Wine/Proton the game is a regular Linux process whose argv[0] is the Windows executable
+// path and whose comm is the executable name (truncated by the kernel to 15 characters).
+#[cfg(target_os = "linux")]
+fn linux_process_names(pid: Pid) -> Vec<String> {
+    let mut names = vec![];
+
+    if let Ok(cmdline) = fs::read(format!("/proc/{}/cmdline", pid)) {
+        let argv0 = cmdline.split(|b| *b == 0).next().unwrap_or(&[]);
+        let argv0 = String::from_utf8_lossy(argv0);
+        if let Some(name) = argv0.rsplit(|c| c == '/' || c == '\\').next() {
+            if !name.is_empty() {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    if let Ok(comm) = fs::read_to_string(format!("/proc/{}/comm", pid)) {
+        names.push(comm.trim_end().to_string());
+    }
+
+    names
+}
+
+#[cfg(target_os = "linux")]
+pub fn get_pid(process_name: &str) -> Option<Pid> {
+    fs::read_dir("/proc").ok()?.find_map(|entry| {
+        let pid = entry.ok()?.file_name().to_str()?.parse::<Pid>().ok()?;
+        if linux_process_names(pid)
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(process_name))
+        {
+            Some(pid)
+        } else {
+            None
+        }
+    })
+}
+
+// Wine maps the PE image straight from the executable, so the mapping of the start of that file
+// is the image base that the offsets in process_details are relative to.
+#[cfg(target_os = "linux")]
+pub fn get_base_address(pid: Pid) -> *const u8 {
+    let names = linux_process_names(pid);
+    let maps = match fs::read_to_string(format!("/proc/{}/maps", pid)) {
+        Ok(maps) => maps,
+        Err(_) => return null(),
+    };
+
+    maps.lines()
+        .find_map(|line| {
+            // Format: address perms offset dev inode pathname
+            let mut fields = line.splitn(6, ' ');
+            let range = fields.next()?;
+            let offset = fields.nth(1)?;
+            let path = fields.nth(2).unwrap_or("").trim();
+            let file_name = path.rsplit('/').next()?;
+
+            if usize::from_str_radix(offset, 16).ok()? != 0
+                || !names.iter().any(|name| name.eq_ignore_ascii_case(file_name))
+            {
+                return None;
+            }
+
+            usize::from_str_radix(range.split('-').next()?, 16).ok()
+        })
+        .map_or(null(), |addr| addr as *const u8)
+}
+
+#[cfg(target_os = "linux")]
+pub fn is_process_running(pid: Pid) -> bool {
+    // Field 3 of /proc/<pid>/stat is the state, an exited but unreaped process shows as a zombie
+    match fs::read_to_string(format!("/proc/{}/stat", pid)) {
+        Ok(stat) => match stat.rsplit(')').next().and_then(|s| s.trim_start().chars().next()) {
+            Some('Z') | Some('X') | None => false,
+            Some(_) => true,
+        },
+        Err(_) => false,
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
 pub fn get_base_address(_pid: Pid) -> *const u8 {
-    panic!("tomb-helper is only supported on Windows");
+    panic!("tomb-helper is only supported on Windows and Linux");
 }
 
-#[cfg(not(windows))]
+#[cfg(not(any(windows, target_os = "linux")))]
 pub fn get_pid(_process_name: &str) -> Option<Pid> {
-    panic!("tomb-helper is only supported on Windows");
+    panic!("tomb-helper is only supported on Windows and Linux");
 }
 
-#[cfg(not(windows))]
+#[cfg(not(any(windows, target_os = "linux")))]
 pub fn is_process_running(_pid: Pid) -> bool {
-    panic!("tomb-helper is only supported on Windows");
+    panic!("tomb-helper is only supported on Windows and Linux");
 }
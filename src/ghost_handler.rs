@@ -0,0 +1,318 @@
+use crate::action::Action;
+use crate::config::GhostConfig;
+use crate::event::Event;
+use crate::handler::Handler;
+use crate::memory_backend::MemoryBackend;
+use crate::process_details::AddressOffsets;
+use crate::recording_handler::{read_trajectory, TrajectorySample};
+use crate::tracked_vector::TrackedVector;
+use std::error::Error;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+// Where the current position is closest to on the reference route
+#[derive(Debug, Copy, Clone)]
+struct RoutePoint {
+    time: f64,
+    distance_along: f32,
+    distance_from: f32,
+}
+
+// Compares Lara's position against a reference route recorded by the recording handler, timed
+// from the StartGhost action
+#[derive(Debug)]
+pub struct GhostHandler {
+    position: TrackedVector,
+    memory: Rc<dyn MemoryBackend>,
+    route: Vec<TrajectorySample>,
+    // Distance travelled along the route at each sample
+    route_distances: Vec<f32>,
+    config: GhostConfig,
+    started: Option<Instant>,
+    last_report: Option<Instant>,
+    last_match_time: Option<f64>,
+    events: Vec<Event>,
+}
+
+impl GhostHandler {
+    pub fn new(
+        address_offsets: &AddressOffsets,
+        base_addr: &usize,
+        memory: &Rc<dyn MemoryBackend>,
+        config: &GhostConfig,
+    ) -> Option<GhostHandler> {
+        let route_file = config.route_file.as_ref()?;
+        println!("Loading ghost handler...");
+
+        let route = match read_trajectory(route_file) {
+            Ok(route) if route.len() >= 2 => route,
+            Ok(_) => {
+                eprintln!("Error: Ghost route {} needs at least 2 samples", route_file);
+                return None;
+            }
+            Err(msg) => {
+                eprintln!("Error: Could not load ghost route from {}: {}", route_file, msg);
+                return None;
+            }
+        };
+        println!(
+            "Loaded ghost route of {} samples, {:.1}s long",
+            route.len(),
+            route.last().unwrap().time
+        );
+
+        Some(GhostHandler {
+            position: TrackedVector::position(address_offsets, *base_addr)?,
+            memory: memory.clone(),
+            route_distances: route_distances(&route),
+            route,
+            config: config.clone(),
+            started: None,
+            last_report: None,
+            last_match_time: None,
+            events: vec![],
+        })
+    }
+
+    // Routes often pass the same place more than once, so only the part of the route within the
+    // search window of the last match (or of the elapsed time, to begin with) is considered
+    fn nearest_point(&self, position: [f32; 3], around_time: f64) -> Option<RoutePoint> {
+        let mut nearest: Option<RoutePoint> = None;
+        for (i, pair) in self.route.windows(2).enumerate() {
+            let (start, end) = (&pair[0], &pair[1]);
+            if end.time < around_time - self.config.search_window
+                || start.time > around_time + self.config.search_window
+            {
+                continue;
+            }
+
+            let segment = difference(start, end);
+            let to_position = [position[0] - start.x, position[1] - start.y, position[2] - start.z];
+            let segment_length_squared = dot(segment, segment);
+            let along = if segment_length_squared > 0.0 {
+                (dot(to_position, segment) / segment_length_squared).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            let offset = [
+                to_position[0] - segment[0] * along,
+                to_position[1] - segment[1] * along,
+                to_position[2] - segment[2] * along,
+            ];
+
+            let point = RoutePoint {
+                time: start.time + (end.time - start.time) * along as f64,
+                distance_along: self.route_distances[i] + (self.route_distances[i + 1] - self.route_distances[i]) * along,
+                distance_from: length(offset),
+            };
+            if nearest.map_or(true, |nearest| point.distance_from < nearest.distance_from) {
+                nearest = Some(point);
+            }
+        }
+        nearest
+    }
+
+    // How far along the route the reference was after the given time
+    fn reference_distance_at(&self, time: f64) -> f32 {
+        let next = self.route.iter().position(|sample| sample.time >= time);
+        match next {
+            Some(0) => 0.0,
+            Some(i) => {
+                let (start, end) = (&self.route[i - 1], &self.route[i]);
+                let along = ((time - start.time) / (end.time - start.time)) as f32;
+                self.route_distances[i - 1] + (self.route_distances[i] - self.route_distances[i - 1]) * along
+            }
+            None => *self.route_distances.last().unwrap(),
+        }
+    }
+
+    fn compare(&mut self) -> Result<(), Box<dyn Error>> {
+        let started = match self.started {
+            Some(started) => started,
+            None => return Ok(()),
+        };
+        let interval = Duration::from_secs_f64(self.config.report_interval);
+        if self.last_report.map_or(false, |last_report| last_report.elapsed() < interval) {
+            return Ok(());
+        }
+        self.last_report = Some(Instant::now());
+
+        // The position can't be read during loading screens, comparisons resume afterwards
+        let position = match self.position.fetch_all(&*self.memory) {
+            Some(position) => position,
+            None => return Ok(()),
+        };
+
+        let elapsed = started.elapsed().as_secs_f64();
+        let nearest = match self.nearest_point(position, self.last_match_time.unwrap_or(elapsed)) {
+            Some(nearest) => nearest,
+            None => return Ok(()),
+        };
+        self.last_match_time = Some(nearest.time);
+
+        let event = Event::GhostComparison {
+            elapsed,
+            time_ahead: nearest.time - elapsed,
+            distance_ahead: nearest.distance_along - self.reference_distance_at(elapsed),
+            distance_from_route: nearest.distance_from,
+        };
+        println!("{}", event);
+        self.events.push(event);
+        Ok(())
+    }
+}
+
+impl Handler for GhostHandler {
+    fn name(&self) -> &str {
+        "ghost"
+    }
+
+    fn status(&self) -> String {
+        match self.started {
+            Some(started) => format!("comparing for {:.1}s", started.elapsed().as_secs_f64()),
+            None => format!("not comparing, route of {} samples loaded", self.route.len()),
+        }
+    }
+
+    fn on_disconnect(&mut self) -> Result<(), Box<dyn Error>> {
+        self.handle_action(Action::StopGhost {})
+    }
+
+    fn rebind(&mut self, base_addr: usize, memory: &Rc<dyn MemoryBackend>) {
+        self.position.rebind(base_addr);
+        self.memory = memory.clone();
+    }
+
+    fn handle_tick(&mut self) -> Result<(), Box<dyn Error>> {
+        self.compare()
+    }
+
+    fn handle_action(&mut self, action: Action) -> Result<(), Box<dyn Error>> {
+        match action {
+            Action::StartGhost {} => {
+                self.started = Some(Instant::now());
+                self.last_report = None;
+                self.last_match_time = None;
+                println!("Started ghost comparison");
+            }
+            Action::StopGhost {} => {
+                if self.started.take().is_some() {
+                    println!("Stopped ghost comparison");
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn take_events(&mut self) -> Vec<Event> {
+        self.events.drain(..).collect()
+    }
+}
+
+fn route_distances(route: &[TrajectorySample]) -> Vec<f32> {
+    let mut route_distances = vec![0.0];
+    for pair in route.windows(2) {
+        let travelled = length(difference(&pair[0], &pair[1]));
+        route_distances.push(route_distances.last().unwrap() + travelled);
+    }
+    route_distances
+}
+
+fn difference(start: &TrajectorySample, end: &TrajectorySample) -> [f32; 3] {
+    [end.x - start.x, end.y - start.y, end.z - start.z]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn length(vector: [f32; 3]) -> f32 {
+    dot(vector, vector).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_backend::InMemoryBackend;
+    use crate::process_details::AddressType;
+    use process_memory::Architecture;
+
+    fn ghost(route: &[(f64, f32, f32)], search_window: f64) -> GhostHandler {
+        let route = route
+            .iter()
+            .map(|&(time, x, y)| TrajectorySample {
+                time,
+                x,
+                y,
+                z: 0.0,
+                look_at: None,
+                camera: None,
+            })
+            .collect::<Vec<_>>();
+        let address_offsets: AddressOffsets = vec![
+            (AddressType::XPosition, vec![0x100]),
+            (AddressType::YPosition, vec![0x104]),
+            (AddressType::ZPosition, vec![0x108]),
+        ]
+        .into_iter()
+        .collect();
+        GhostHandler {
+            position: TrackedVector::position(&address_offsets, 0).unwrap(),
+            memory: Rc::new(InMemoryBackend::new(Architecture::Arch64Bit)),
+            route_distances: route_distances(&route),
+            route,
+            config: GhostConfig {
+                search_window,
+                ..GhostConfig::default()
+            },
+            started: None,
+            last_report: None,
+            last_match_time: None,
+            events: vec![],
+        }
+    }
+
+    fn assert_point(point: Option<RoutePoint>, time: f64, distance_along: f32, distance_from: f32) {
+        let point = point.expect("no point on the route");
+        assert!((point.time - time).abs() < 1e-6, "time {} != {}", point.time, time);
+        assert!(
+            (point.distance_along - distance_along).abs() < 1e-4,
+            "distance along {} != {}",
+            point.distance_along,
+            distance_along
+        );
+        assert!(
+            (point.distance_from - distance_from).abs() < 1e-4,
+            "distance from {} != {}",
+            point.distance_from,
+            distance_from
+        );
+    }
+
+    #[test]
+    fn finds_the_nearest_point_between_samples() {
+        let ghost = ghost(&[(0.0, 0.0, 0.0), (1.0, 10.0, 0.0), (2.0, 10.0, 10.0)], 30.0);
+
+        assert_point(ghost.nearest_point([5.0, 3.0, 0.0], 0.0), 0.5, 5.0, 3.0);
+        assert_point(ghost.nearest_point([12.0, 5.0, 0.0], 0.0), 1.5, 15.0, 2.0);
+    }
+
+    #[test]
+    fn stops_at_the_ends_of_the_route() {
+        let ghost = ghost(&[(0.0, 0.0, 0.0), (1.0, 10.0, 0.0)], 30.0);
+
+        assert_point(ghost.nearest_point([-3.0, 4.0, 0.0], 0.0), 0.0, 0.0, 5.0);
+        assert_point(ghost.nearest_point([13.0, 0.0, 0.0], 0.0), 1.0, 10.0, 3.0);
+    }
+
+    #[test]
+    fn only_searches_around_the_given_time() {
+        // Out along the X axis and back again, so every point is passed twice
+        let ghost = ghost(&[(0.0, 0.0, 0.0), (10.0, 10.0, 0.0), (20.0, 0.0, 0.0)], 5.0);
+
+        assert_point(ghost.nearest_point([2.0, 0.0, 0.0], 2.0), 2.0, 2.0, 0.0);
+        assert_point(ghost.nearest_point([2.0, 0.0, 0.0], 18.0), 18.0, 18.0, 0.0);
+        assert!(ghost.nearest_point([2.0, 0.0, 0.0], 40.0).is_none());
+    }
+}
@@ -1,7 +1,45 @@
 use crate::action::Action;
+use crate::event::{Event, EventCategory};
+use crate::memory_backend::MemoryBackend;
 use std::error::Error;
+use std::rc::Rc;
 
 pub trait Handler {
+    // Shown alongside the status and in errors from the handler
+    fn name(&self) -> &str;
+    // A short summary of what the handler is currently doing
+    fn status(&self) -> String;
+
     fn handle_tick(&mut self) -> Result<(), Box<dyn Error>>;
+    // Ticks per second when handler_tick_rates doesn't set one, instead of the global tick_rate
+    fn default_tick_rate(&self) -> Option<f64> {
+        None
+    }
     fn handle_action(&mut self, action: Action) -> Result<(), Box<dyn Error>>;
+
+    // Called on every connection to the game once all handlers have been built or rebound, before
+    // the first tick
+    fn on_connect(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+    // Called when the game exits, the game's memory can no longer be read by this point so this
+    // is only for flushing state
+    fn on_disconnect(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+    // Called when the same game version is started again, so that the handler reads from the new
+    // process while keeping the rest of its state
+    fn rebind(&mut self, base_addr: usize, memory: &Rc<dyn MemoryBackend>);
+
+    // Events raised since this was last called, which the event bus passes on to subscribers
+    fn take_events(&mut self) -> Vec<Event> {
+        vec![]
+    }
+    // The categories of events passed to handle_event
+    fn subscriptions(&self) -> Vec<EventCategory> {
+        vec![]
+    }
+    fn handle_event(&mut self, _event: &Event) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
 }
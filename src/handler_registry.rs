@@ -0,0 +1,200 @@
+use crate::action::Action;
+use crate::api_handler::ApiHandler;
+use crate::config::{Config, CutsceneTiming, HttpApi};
+use crate::cutscene_handler::CutsceneHandler;
+use crate::cutscene_timing_generator_handler::CutsceneTimingGeneratorHandler;
+use crate::ghost_handler::GhostHandler;
+use crate::handler::Handler;
+use crate::memory_backend::MemoryBackend;
+use crate::position_handler::PositionHandler;
+use crate::process_details::ProcessDetails;
+use crate::recording_handler::RecordingHandler;
+use crate::saved_positions::PositionStore;
+use crate::segment_handler::SegmentHandler;
+use crate::telemetry_handler::TelemetryHandler;
+use crate::text_output_handler::TextOutputHandler;
+use crate::trigger_zone_handler::TriggerZoneHandler;
+use std::rc::Rc;
+use std::sync::mpsc::Sender;
+
+// Everything handlers are built from when connecting to a game
+pub struct HandlerContext<'a> {
+    pub config: &'a Config,
+    pub details: &'a ProcessDetails,
+    pub base_addr: usize,
+    pub memory: &'a Rc<dyn MemoryBackend>,
+    pub position_store: &'a PositionStore,
+    pub actions: &'a Sender<Action>,
+}
+
+// Builders return None if the handler is turned off or the game doesn't support it
+type HandlerBuilder = fn(&HandlerContext) -> Option<Box<dyn Handler>>;
+
+// Handlers are built, and receive actions, in this order
+const HANDLER_BUILDERS: &[HandlerBuilder] = &[
+    build_cutscene_handler,
+    build_position_handler,
+    build_look_at_handler,
+    build_recording_handler,
+    build_ghost_handler,
+    build_trigger_zone_handler,
+    build_segment_handler,
+    build_telemetry_handler,
+    build_text_output_handler,
+    build_api_handler,
+];
+
+pub fn build_handlers(context: &HandlerContext) -> Vec<Box<dyn Handler>> {
+    HANDLER_BUILDERS
+        .iter()
+        .filter_map(|build| build(context))
+        .collect()
+}
+
+// The handlers for one game version, kept between connections so that e.g. the cutscene skip
+// totals survive the game crashing and being restarted
+pub struct HandlerSet {
+    game: String,
+    version: String,
+    pub handlers: Vec<Box<dyn Handler>>,
+}
+
+impl HandlerSet {
+    // Rebinds the previous handlers to the new process if it is the same game version, otherwise
+    // builds new ones
+    pub fn bind(previous: Option<HandlerSet>, context: &HandlerContext) -> HandlerSet {
+        let details = context.details;
+        match previous {
+            Some(mut previous) if previous.game == details.name && previous.version == details.version.version => {
+                println!("Reusing handlers from the last connection to {} {}", details.name, details.version.version);
+                for handler in &mut previous.handlers {
+                    handler.rebind(context.base_addr, context.memory);
+                }
+                previous
+            }
+            previous => {
+                // Dropped before building the new handlers so that e.g. the HTTP API port is free
+                drop(previous);
+                HandlerSet {
+                    game: details.name.clone(),
+                    version: details.version.version.clone(),
+                    handlers: build_handlers(context),
+                }
+            }
+        }
+    }
+}
+
+fn build_cutscene_handler(context: &HandlerContext) -> Option<Box<dyn Handler>> {
+    let details = context.details;
+    match &context.config.record_cutscene_timing {
+        CutsceneTiming::On { timing_file, livesplit_port } => Some(Box::new(CutsceneTimingGeneratorHandler::new(
+            &details.address_offsets,
+            &context.base_addr,
+            context.memory,
+            timing_file,
+            livesplit_port,
+        )?)),
+        CutsceneTiming::Off {} => Some(Box::new(CutsceneHandler::new(
+            &details.address_offsets,
+            &context.base_addr,
+            context.memory,
+            &context.config.cutscene_blacklist_file,
+            &context.config.cutscene_timing_file,
+        )?)),
+    }
+}
+
+fn build_position_handler(context: &HandlerContext) -> Option<Box<dyn Handler>> {
+    Some(Box::new(PositionHandler::new_position_handler(
+        &context.details.address_offsets,
+        &context.base_addr,
+        context.memory,
+        &context.config.position_slots,
+        context.position_store,
+        &context.config.fly,
+    )?))
+}
+
+fn build_look_at_handler(context: &HandlerContext) -> Option<Box<dyn Handler>> {
+    Some(Box::new(PositionHandler::new_look_at_handler(
+        &context.details.address_offsets,
+        &context.base_addr,
+        context.memory,
+        &context.config.position_slots,
+        context.position_store,
+        &context.config.fly,
+    )?))
+}
+
+fn build_recording_handler(context: &HandlerContext) -> Option<Box<dyn Handler>> {
+    Some(Box::new(RecordingHandler::new(
+        &context.details.address_offsets,
+        &context.base_addr,
+        context.memory,
+        &context.config.recording,
+    )?))
+}
+
+fn build_ghost_handler(context: &HandlerContext) -> Option<Box<dyn Handler>> {
+    Some(Box::new(GhostHandler::new(
+        &context.details.address_offsets,
+        &context.base_addr,
+        context.memory,
+        &context.config.ghost,
+    )?))
+}
+
+fn build_trigger_zone_handler(context: &HandlerContext) -> Option<Box<dyn Handler>> {
+    Some(Box::new(TriggerZoneHandler::new(
+        &context.details.address_offsets,
+        &context.base_addr,
+        context.memory,
+        &context.config.trigger_zones,
+        &context.details.name,
+        context.actions,
+    )?))
+}
+
+fn build_segment_handler(context: &HandlerContext) -> Option<Box<dyn Handler>> {
+    Some(Box::new(SegmentHandler::new(
+        &context.details.address_offsets,
+        &context.base_addr,
+        context.memory,
+        &context.config.segments,
+        &context.config.segment_history_file,
+        &context.details.name,
+        context.actions,
+    )?))
+}
+
+fn build_telemetry_handler(context: &HandlerContext) -> Option<Box<dyn Handler>> {
+    Some(Box::new(TelemetryHandler::new(
+        &context.details.address_offsets,
+        &context.base_addr,
+        context.memory,
+        &context.config.telemetry,
+    )?))
+}
+
+fn build_text_output_handler(context: &HandlerContext) -> Option<Box<dyn Handler>> {
+    Some(Box::new(TextOutputHandler::new(
+        &context.details.address_offsets,
+        &context.base_addr,
+        context.memory,
+        &context.config.text_outputs,
+    )?))
+}
+
+fn build_api_handler(context: &HandlerContext) -> Option<Box<dyn Handler>> {
+    match context.config.http_api {
+        HttpApi::On { port } => Some(Box::new(ApiHandler::new(
+            context.details,
+            &context.base_addr,
+            context.memory,
+            &port,
+            context.actions,
+        )?)),
+        HttpApi::Off {} => None,
+    }
+}
@@ -0,0 +1,68 @@
+use crate::action::Action;
+use crate::config::FlyHotkey;
+use livesplit_hotkey::KeyCode;
+use std::collections::HashSet;
+
+// The hotkey hook only reports key presses, so held keys are found by polling the keyboard state
+// and turned into press and release actions
+#[derive(Debug, Clone)]
+pub struct KeyStatePoller {
+    hotkeys: Vec<FlyHotkey>,
+    held: HashSet<KeyCode>,
+}
+
+impl KeyStatePoller {
+    pub fn new(hotkeys: &Vec<FlyHotkey>) -> KeyStatePoller {
+        if !hotkeys.is_empty() && !is_supported() {
+            eprintln!("Error: Held keys for fly mode are only supported on Windows, the fly keys won't do anything");
+        }
+
+        KeyStatePoller {
+            hotkeys: hotkeys.clone(),
+            held: HashSet::new(),
+        }
+    }
+
+    pub fn poll(&mut self) -> Vec<Action> {
+        let mut actions = vec![];
+        let mut held = HashSet::new();
+        for hotkey in &self.hotkeys {
+            let down = is_key_down(hotkey.key);
+            let was_down = self.held.contains(&hotkey.key);
+            if down {
+                held.insert(hotkey.key);
+            }
+
+            if down && !was_down {
+                actions.push(Action::FlyInputPressed { input: hotkey.input });
+            } else if !down && was_down {
+                actions.push(Action::FlyInputReleased { input: hotkey.input });
+            }
+        }
+
+        self.held = held;
+        actions
+    }
+}
+
+// Whether held keys can be detected on this platform, fly mode needs them
+#[cfg(windows)]
+pub fn is_supported() -> bool {
+    true
+}
+
+// livesplit_hotkey's Windows key codes are the virtual key codes
+#[cfg(windows)]
+fn is_key_down(key: KeyCode) -> bool {
+    unsafe { winapi::um::winuser::GetAsyncKeyState(key as i32) as u16 & 0x8000 != 0 }
+}
+
+#[cfg(not(windows))]
+pub fn is_supported() -> bool {
+    false
+}
+
+#[cfg(not(windows))]
+fn is_key_down(_key: KeyCode) -> bool {
+    false
+}
@@ -1,108 +1,166 @@
 use crate::action::Action;
-use crate::config::{Hotkey, CutsceneTiming, Config};
+use crate::config::{Hotkey, Config, EventStreamConfig};
+use crate::event::Event;
+use crate::event_bus::EventBus;
+use crate::event_stream::EventStream;
+use crate::find_process::SignatureFallback;
 use crate::handler::Handler;
-use crate::cutscene_handler::CutsceneHandler;
-use crate::cutscene_timing_generator_handler::CutsceneTimingGeneratorHandler;
-use crate::position_handler::PositionHandler;
+use crate::handler_registry::{HandlerContext, HandlerSet};
+use crate::key_state::KeyStatePoller;
+use crate::memory_backend::MemoryBackend;
 use crate::process_details::ProcessDetails;
+use crate::saved_positions::PositionStore;
+use crate::scheduler::TickScheduler;
+use crate::version_report::UnknownVersionReporter;
 use process_memory::Pid;
 use itertools::Itertools;
 use livesplit_hotkey::Hook;
 use livesplit_hotkey::KeyCode;
-use std::sync::mpsc;
-use std::{thread, time};
+use std::rc::Rc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::{env, thread, time};
 
 mod action;
+mod api_handler;
+mod atomic_file;
+mod capabilities;
 mod config;
 mod cutscene_handler;
 mod cutscene_timing_generator_handler;
 mod position_handler;
 mod handler;
+mod handler_registry;
+mod key_state;
+mod memory_backend;
 mod process_details;
 mod find_process;
+mod error;
+mod event;
+mod event_bus;
+mod event_stream;
+mod ghost_handler;
 mod tracked_memory;
+mod tracked_vector;
+mod trigger_zone_handler;
 mod cutscene_timing_info;
 mod readable_from_path;
+mod recording_handler;
+mod saved_positions;
+mod scheduler;
+mod segment_handler;
+mod signature_scan;
+mod telemetry_handler;
+mod text_output_handler;
+mod version_fingerprint;
+mod version_report;
 
 #[cfg(windows)]
 extern crate winapi;
 
 fn main() {
     let config = config::get_config();
+    let process_details = process_details::load_process_details(&config.version_table_file);
+
+    let args: Vec<String> = env::args().collect();
+    match args.get(1).map(String::as_str) {
+        Some("--version-report") => {
+            version_report::report_running_games(&process_details, &args.get(2).cloned());
+            return;
+        }
+        Some("--list-supported") => {
+            capabilities::print_supported_matrix(&process_details);
+            if !key_state::is_supported() {
+                println!();
+                println!("Held keys for fly mode are only supported on Windows, the fly keys won't do anything here");
+            }
+            return;
+        }
+        _ => {}
+    }
+
     print_help(&config.hotkeys);
+    let (tx, rx) = mpsc::channel();
+    let event_stream = match config.event_stream {
+        EventStreamConfig::On { port } => EventStream::start(port)
+            .map_err(|msg| eprintln!("Error: Could not start event stream on port {}: {}", port, msg))
+            .ok(),
+        EventStreamConfig::Off {} => None,
+    };
+    let mut session = Session {
+        event_stream,
+        actions: tx,
+        action_receiver: rx,
+        handlers: None,
+    };
+    let mut unknown_version_reporter = UnknownVersionReporter::new(config.unknown_version_report_file.clone());
+    // A forced version is always used as is, so there is nothing to fall back from
+    let mut signature_fallback = match config.force_version {
+        None if config.signature_scan_unknown_versions => Some(SignatureFallback::default()),
+        _ => None,
+    };
     println!("Searching for Tomb Raider processes...");
     loop {
-        find_process::find_process(
-            process_details::known_process_details(),
-            config.force_version.clone(),
-        )
-        .and_then(|(pid, handle, base_addr, details)| Some(connect(config.clone(), pid, handle, base_addr, details)));
+        let found = find_process::find_process(process_details.clone(), config.force_version.clone())
+            .or_else(|| signature_fallback.as_mut()?.find_process(&process_details));
+        match found {
+            Some((pid, memory, base_addr, details)) => {
+                connect(config.clone(), pid, memory, base_addr, details, &mut session)
+            }
+            None => unknown_version_reporter.check(&process_details),
+        }
 
         thread::sleep(time::Duration::from_millis(100));
     }
 }
 
+// Everything that lasts for as long as tomb-helper runs, rather than for one connection to the game
+struct Session {
+    event_stream: Option<EventStream>,
+    actions: Sender<Action>,
+    action_receiver: Receiver<Action>,
+    handlers: Option<HandlerSet>,
+}
+
 fn connect(
     config: Config,
     pid: Pid,
-    handle: process_memory::ProcessHandle,
+    memory: Rc<dyn MemoryBackend>,
     base_addr: usize,
-    details: ProcessDetails
+    mut details: ProcessDetails,
+    session: &mut Session,
 ) {
     println!("Connecting to {} {} with PID {}", details.name, details.version.version, pid);
 
-    let mut handlers: Vec<Box<dyn Handler>> = vec![];
-
-    match config.record_cutscene_timing {
-        CutsceneTiming::On { timing_file, livesplit_port } => {
-            match CutsceneTimingGeneratorHandler::new(
-                &details.address_offsets,
-                &details.arch,
-                &base_addr,
-                &handle,
-                &timing_file,
-                &livesplit_port,
-            ) {
-                Some(h) => handlers.push(Box::new(h)),
-                None => {},
-            }
-        },
-        CutsceneTiming::Off {} => {
-            match CutsceneHandler::new(
-                &details.address_offsets,
-                &details.arch,
-                &base_addr,
-                &handle,
-                &config.cutscene_blacklist_file,
-                &config.cutscene_timing_file,
-            ) {
-                Some(h) => handlers.push(Box::new(h)),
-                None => {},
-            }
-        },
-    }
+    signature_scan::resolve_signatures(&*memory, base_addr, &mut details);
+    capabilities::print_capability_report(&details, &*memory, base_addr);
 
-    match PositionHandler::new_position_handler(
-        &details.address_offsets,
-        &details.arch,
-        &base_addr,
-        &handle,
-    ) {
-        Some(h) => handlers.push(Box::new(h)),
-        None => {},
-    }
+    let tx = session.actions.clone();
+    // Anything sent while disconnected, e.g. over the HTTP API, was meant for the old process
+    while session.action_receiver.try_recv().is_ok() {}
 
-    match PositionHandler::new_look_at_handler(
-        &details.address_offsets,
-        &details.arch,
-        &base_addr,
-        &handle,
-    ) {
-        Some(h) => handlers.push(Box::new(h)),
-        None => {},
-    }
+    let position_store = PositionStore::new(
+        &config.saved_positions_file,
+        &details.name,
+        &details.version.version,
+    );
 
-    let (tx, rx) = mpsc::channel();
+    let mut handler_set = HandlerSet::bind(
+        session.handlers.take(),
+        &HandlerContext {
+            config: &config,
+            details: &details,
+            base_addr,
+            memory: &memory,
+            position_store: &position_store,
+            actions: &tx,
+        },
+    );
+    let handlers = &mut handler_set.handlers;
+    for handler in handlers.iter_mut() {
+        handler
+            .on_connect()
+            .unwrap_or_else(|msg| eprintln!("Error: {}: {}", handler.name(), msg));
+    }
 
     let hook = Hook::new().unwrap();
     let key_groups = config
@@ -116,38 +174,109 @@ fn connect(
         let current_tx = tx.clone();
         hook.register(key, move || {
             for action in &actions {
-                current_tx.send(*action).unwrap();
+                current_tx.send(action.clone()).unwrap();
             }
         })
         .unwrap();
     }
 
+    let mut key_state_poller = KeyStatePoller::new(&config.fly.hotkeys);
+
+    print_status(handlers);
     println!("Started!");
+    let connected = Event::Connected {
+        game: details.name.clone(),
+        version: details.version.version.clone(),
+    };
+    let mut event_bus = EventBus::new();
+    if let Some(event_stream) = &session.event_stream {
+        event_bus.add_sink(Box::new(event_stream.clone()));
+    }
+    event_bus.emit(connected);
+
+    let mut scheduler = TickScheduler::new(handlers, &config.scheduler);
+    let process_check_interval = time::Duration::from_secs_f64(config.scheduler.process_check_interval);
+    let mut next_process_check = time::Instant::now() + process_check_interval;
+    let mut checked_after_failure = false;
 
     loop {
-        if !find_process::is_process_running(pid) {
-            println!("Disconnected from {} {} with PID {}", details.name, details.version.version, pid);
-            return;
+        let now = time::Instant::now();
+        if now >= next_process_check {
+            next_process_check = now + process_check_interval;
+            checked_after_failure = false;
+            if !find_process::is_process_running(pid) {
+                println!("Disconnected from {} {} with PID {}", details.name, details.version.version, pid);
+                let disconnected = Event::Disconnected {
+                    game: details.name.clone(),
+                    version: details.version.version.clone(),
+                };
+                event_bus.emit(disconnected);
+                event_bus.route(handlers);
+                for handler in handlers.iter_mut() {
+                    handler
+                        .on_disconnect()
+                        .unwrap_or_else(|msg| eprintln!("Error: {}: {}", handler.name(), msg));
+                }
+                // Deliver anything the handlers emitted while disconnecting
+                event_bus.route(handlers);
+                session.handlers = Some(handler_set);
+                return;
+            }
+        }
+
+        for action in key_state_poller.poll() {
+            tx.send(action).unwrap();
+        }
+
+        // Ticks fail once the game has exited, so check straight away rather than repeating the
+        // errors until the next process check. A handler that keeps failing while the game runs
+        // only causes one extra check per process check interval.
+        let mut process_exited = false;
+        for index in scheduler.due(now) {
+            let handler = &mut handlers[index];
+            if let Err(msg) = handler.handle_tick() {
+                if !checked_after_failure {
+                    checked_after_failure = true;
+                    if !find_process::is_process_running(pid) {
+                        process_exited = true;
+                        break;
+                    }
+                }
+                eprintln!("Error: {}: {}", handler.name(), msg);
+            }
         }
+        if process_exited {
+            next_process_check = now;
+            continue;
+        }
+
+        event_bus.route(handlers);
 
-        let signal = rx.try_recv();
+        // Sleep until the next tick or process check is due, unless an action comes in first
+        let wake = scheduler
+            .next_tick()
+            .map_or(next_process_check, |next_tick| next_tick.min(next_process_check));
+        let signal = session
+            .action_receiver
+            .recv_timeout(wake.saturating_duration_since(time::Instant::now()));
 
         match signal {
+            Ok(Action::PrintStatus {}) => print_status(handlers),
             Ok(s) => {
-                for handler in &mut handlers {
+                for handler in handlers.iter_mut() {
                     handler
-                        .handle_action(s)
-                        .unwrap_or_else(|msg| eprintln!("Error: {}", msg));
+                        .handle_action(s.clone())
+                        .unwrap_or_else(|msg| eprintln!("Error: {}: {}", handler.name(), msg));
                 }
             }
             _ => {}
         }
+    }
+}
 
-        for handler in &mut handlers {
-            handler
-                .handle_tick()
-                .unwrap_or_else(|msg| eprintln!("Error: {}", msg));
-        }
+fn print_status(handlers: &[Box<dyn Handler>]) {
+    for handler in handlers {
+        println!("{}: {}", handler.name(), handler.status());
     }
 }
 
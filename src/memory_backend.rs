@@ -0,0 +1,221 @@
+use process_memory::{Architecture, ProcessHandle};
+#[cfg(not(target_os = "linux"))]
+use process_memory::{CopyAddress, PutAddress};
+#[cfg(test)]
+use std::cell::RefCell;
+#[cfg(test)]
+use std::collections::HashMap;
+use std::{fmt, io, mem, slice};
+#[cfg(target_os = "linux")]
+use std::{fs::OpenOptions, os::unix::fs::FileExt};
+
+pub trait MemoryBackend: fmt::Debug {
+    fn arch(&self) -> Architecture;
+    fn read_bytes(&self, addr: usize, buf: &mut [u8]) -> io::Result<()>;
+    fn write_bytes(&self, addr: usize, buf: &[u8]) -> io::Result<()>;
+
+    fn read_pointer(&self, addr: usize) -> io::Result<usize> {
+        match self.arch() {
+            Architecture::Arch64Bit => {
+                let mut bytes = [0_u8; 8];
+                self.read_bytes(addr, &mut bytes)?;
+                Ok(u64::from_le_bytes(bytes) as usize)
+            }
+            _ => {
+                let mut bytes = [0_u8; 4];
+                self.read_bytes(addr, &mut bytes)?;
+                Ok(u32::from_le_bytes(bytes) as usize)
+            }
+        }
+    }
+
+    // Follows a pointer chain the same way process_memory::DataMember does: every offset but the
+    // last is added to the current address and dereferenced, the last one is only added.
+    fn resolve_pointer_chain(&self, offsets: &[usize]) -> io::Result<usize> {
+        let (last, pointers) = offsets.split_last().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, "Empty pointer chain")
+        })?;
+
+        let mut addr = 0_usize;
+        for offset in pointers {
+            addr = self.read_pointer(addr.wrapping_add(*offset))?;
+        }
+        Ok(addr.wrapping_add(*last))
+    }
+
+    // Follows a pointer chain from the version table, where the first offset is relative to the
+    // game's base address
+    fn resolve_from_base(&self, offsets: &[usize], base_addr: usize) -> io::Result<usize> {
+        let mut offsets_with_base = offsets.to_vec();
+        if let Some(first) = offsets_with_base.first_mut() {
+            *first = first.wrapping_add(base_addr);
+        }
+        self.resolve_pointer_chain(&offsets_with_base)
+    }
+}
+
+pub fn read_value<T: Copy>(memory: &dyn MemoryBackend, addr: usize) -> io::Result<T> {
+    let mut bytes = vec![0_u8; mem::size_of::<T>()];
+    memory.read_bytes(addr, &mut bytes)?;
+    Ok(unsafe { (bytes.as_ptr() as *const T).read_unaligned() })
+}
+
+pub fn write_value<T: Copy>(memory: &dyn MemoryBackend, addr: usize, value: &T) -> io::Result<()> {
+    let bytes = unsafe { slice::from_raw_parts(value as *const T as *const u8, mem::size_of::<T>()) };
+    memory.write_bytes(addr, bytes)
+}
+
+#[derive(Debug, Clone)]
+pub struct ProcessBackend {
+    handle: ProcessHandle,
+    arch: Architecture,
+}
+
+impl ProcessBackend {
+    pub fn new(handle: ProcessHandle, arch: Architecture) -> ProcessBackend {
+        ProcessBackend { handle, arch }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl MemoryBackend for ProcessBackend {
+    fn arch(&self) -> Architecture {
+        self.arch
+    }
+
+    fn read_bytes(&self, addr: usize, buf: &mut [u8]) -> io::Result<()> {
+        self.handle.copy_address(addr, buf)
+    }
+
+    fn write_bytes(&self, addr: usize, buf: &[u8]) -> io::Result<()> {
+        self.handle.put_address(addr, buf)
+    }
+}
+
+// On Linux the process handle is just the PID. process_vm_readv/writev are tried first as they need
+// no file descriptor, but they refuse to touch read-only pages, which /proc/<pid>/mem allows.
+#[cfg(target_os = "linux")]
+impl MemoryBackend for ProcessBackend {
+    fn arch(&self) -> Architecture {
+        self.arch
+    }
+
+    fn read_bytes(&self, addr: usize, buf: &mut [u8]) -> io::Result<()> {
+        let local = libc::iovec {
+            iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        };
+        let remote = libc::iovec {
+            iov_base: addr as *mut libc::c_void,
+            iov_len: buf.len(),
+        };
+        let read = unsafe { libc::process_vm_readv(self.handle, &local, 1, &remote, 1, 0) };
+        if read == buf.len() as isize {
+            return Ok(());
+        }
+
+        OpenOptions::new()
+            .read(true)
+            .open(format!("/proc/{}/mem", self.handle))?
+            .read_exact_at(buf, addr as u64)
+    }
+
+    fn write_bytes(&self, addr: usize, buf: &[u8]) -> io::Result<()> {
+        let local = libc::iovec {
+            iov_base: buf.as_ptr() as *mut libc::c_void,
+            iov_len: buf.len(),
+        };
+        let remote = libc::iovec {
+            iov_base: addr as *mut libc::c_void,
+            iov_len: buf.len(),
+        };
+        let written = unsafe { libc::process_vm_writev(self.handle, &local, 1, &remote, 1, 0) };
+        if written == buf.len() as isize {
+            return Ok(());
+        }
+
+        OpenOptions::new()
+            .write(true)
+            .open(format!("/proc/{}/mem", self.handle))?
+            .write_all_at(buf, addr as u64)
+    }
+}
+
+// A fake process made of sparse bytes, for exercising handlers without a running game.
+// Reading any byte that has not been written is an error, like reading unmapped memory.
+#[cfg(test)]
+#[derive(Debug)]
+pub struct InMemoryBackend {
+    arch: Architecture,
+    bytes: RefCell<HashMap<usize, u8>>,
+}
+
+#[cfg(test)]
+impl InMemoryBackend {
+    pub fn new(arch: Architecture) -> InMemoryBackend {
+        InMemoryBackend {
+            arch,
+            bytes: RefCell::new(HashMap::new()),
+        }
+    }
+
+    pub fn set<T: Copy>(&self, addr: usize, value: T) {
+        write_value(self, addr, &value).unwrap();
+    }
+
+    pub fn get<T: Copy>(&self, addr: usize) -> io::Result<T> {
+        read_value(self, addr)
+    }
+
+    pub fn set_pointer(&self, addr: usize, target: usize) {
+        match self.arch {
+            Architecture::Arch64Bit => self.set(addr, target as u64),
+            _ => self.set(addr, target as u32),
+        }
+    }
+}
+
+#[cfg(test)]
+impl MemoryBackend for InMemoryBackend {
+    fn arch(&self) -> Architecture {
+        self.arch
+    }
+
+    fn read_bytes(&self, addr: usize, buf: &mut [u8]) -> io::Result<()> {
+        let bytes = self.bytes.borrow();
+        for (i, byte) in buf.iter_mut().enumerate() {
+            *byte = *bytes.get(&addr.wrapping_add(i)).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Address {:#x} is not mapped", addr.wrapping_add(i)),
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    fn write_bytes(&self, addr: usize, buf: &[u8]) -> io::Result<()> {
+        let mut bytes = self.bytes.borrow_mut();
+        for (i, byte) in buf.iter().enumerate() {
+            bytes.insert(addr.wrapping_add(i), *byte);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_pointer_chains_from_the_base_address() {
+        let memory = InMemoryBackend::new(Architecture::Arch64Bit);
+        memory.set_pointer(0x1_0100, 0x2000);
+        memory.set_pointer(0x2010, 0x3000);
+
+        assert_eq!(memory.resolve_from_base(&[0x100], 0x1_0000).unwrap(), 0x1_0100);
+        assert_eq!(memory.resolve_from_base(&[0x100, 0x10, 0x8], 0x1_0000).unwrap(), 0x3008);
+        assert!(memory.resolve_from_base(&[0x200, 0x10], 0x1_0000).is_err());
+        assert!(memory.resolve_from_base(&[], 0x1_0000).is_err());
+    }
+}
@@ -1,230 +1,873 @@
-use crate::action::Action;
+use crate::action::{Action, FlyInput, PositionKind};
+use crate::config::FlyConfig;
+use crate::error::HelperError;
+use crate::event::Event;
 use crate::handler::Handler;
+use crate::memory_backend::MemoryBackend;
 use crate::process_details::{AddressOffsets, AddressType};
+use crate::saved_positions::{PositionStore, SavedPosition, SavedSlots};
 use crate::tracked_memory::TrackedMemory;
-use process_memory::{Architecture, ProcessHandle};
+use crate::tracked_vector::TrackedVector;
+use std::collections::{HashSet, VecDeque};
 use std::error::Error;
-use std::fmt;
 use std::io;
+use std::rc::Rc;
+use std::time::Instant;
+
+const TELEPORT_HISTORY_LENGTH: usize = 100;
+
+// Longest time a single fly tick can cover, so that a stall (e.g. a loading screen) doesn't fling
+// Lara across the map
+const MAX_FLY_TICK_SECONDS: f32 = 0.1;
 
 #[derive(Debug, Clone)]
 pub struct PositionHandler {
     active: bool,
-    position: TrackedPosition,
-    saved_position: TrackedPosition,
+    position: TrackedVector,
+    // Where the position is held while the handler is active
+    coordinates: [f32; 3],
+    // The rotation and velocity are only captured when storing and reapplied when restoring, unlike
+    // the coordinates they aren't held while the handler is active
+    rotation: Option<TrackedVector>,
+    velocity: Option<TrackedVector>,
+    saved_positions: SavedSlots,
+    position_store: PositionStore,
+    current_slot: String,
+    slot_count: u32,
+    undo_history: VecDeque<[f32; 3]>,
+    redo_history: Vec<[f32; 3]>,
+    flying: bool,
+    fly_config: FlyConfig,
+    held_inputs: HashSet<FlyInput>,
+    fly_velocity: [f32; 3],
+    last_fly_tick: Instant,
     camera_rotation: Option<(TrackedMemory<f32>, TrackedMemory<f32>)>,
-    handle: ProcessHandle,
-    name: String,
+    camera_matrix: Option<[TrackedMemory<[f32; 3]>; 3]>,
+    // Whether the camera rotation and matrix could be read on the last tick
+    camera_rotation_readable: bool,
+    camera_matrix_readable: bool,
+    memory: Rc<dyn MemoryBackend>,
+    kind: PositionKind,
+    events: Vec<Event>,
 }
 
 impl PositionHandler {
     pub fn new_position_handler(
         address_offsets: &AddressOffsets,
-        arch: &Architecture,
         base_addr: &usize,
-        handle: &ProcessHandle,
+        memory: &Rc<dyn MemoryBackend>,
+        slot_count: &u32,
+        position_store: &PositionStore,
+        fly_config: &FlyConfig,
     ) -> Option<PositionHandler> {
         println!("Loading position handler...");
 
-        Some(PositionHandler {
+        let mut handler = PositionHandler {
             active: false,
-            position: TrackedPosition::new(
-                address_offsets.get(&AddressType::XPosition)?.clone(),
-                address_offsets.get(&AddressType::YPosition)?.clone(),
-                address_offsets.get(&AddressType::ZPosition)?.clone(),
-                *arch,
+            position: TrackedVector::position(address_offsets, *base_addr)?,
+            coordinates: [0.0; 3],
+            rotation: TrackedVector::new(
+                address_offsets,
+                [AddressType::XRotation, AddressType::YRotation, AddressType::ZRotation],
                 *base_addr,
             ),
-            saved_position: TrackedPosition::new(
-                address_offsets.get(&AddressType::XPosition)?.clone(),
-                address_offsets.get(&AddressType::YPosition)?.clone(),
-                address_offsets.get(&AddressType::ZPosition)?.clone(),
-                *arch,
+            velocity: TrackedVector::new(
+                address_offsets,
+                [AddressType::XVelocity, AddressType::YVelocity, AddressType::ZVelocity],
                 *base_addr,
             ),
+            saved_positions: position_store.load(PositionKind::Position.name()),
+            position_store: position_store.clone(),
+            current_slot: "1".to_string(),
+            slot_count: *slot_count,
+            undo_history: VecDeque::new(),
+            redo_history: vec![],
+            flying: false,
+            fly_config: fly_config.clone(),
+            held_inputs: HashSet::new(),
+            fly_velocity: [0.0; 3],
+            last_fly_tick: Instant::now(),
             camera_rotation: match (
                 address_offsets.get(&AddressType::CameraSin),
                 address_offsets.get(&AddressType::CameraCos),
             ) {
                 (Some(sin), Some(cos)) => Some((
-                    TrackedMemory::new(0.0, sin.clone(), *arch, *base_addr),
-                    TrackedMemory::new(0.0, cos.clone(), *arch, *base_addr),
+                    TrackedMemory::new(0.0, sin.clone(), *base_addr),
+                    TrackedMemory::new(0.0, cos.clone(), *base_addr),
                 )),
                 _ => None,
             },
-            handle: *handle,
-            name: "position".to_string(),
-        })
+            camera_matrix: match (
+                address_offsets.get(&AddressType::CameraRotationX),
+                address_offsets.get(&AddressType::CameraRotationY),
+                address_offsets.get(&AddressType::CameraRotationZ),
+            ) {
+                (Some(x), Some(y), Some(z)) => Some([
+                    TrackedMemory::new([0.0; 3], x.clone(), *base_addr),
+                    TrackedMemory::new([0.0; 3], y.clone(), *base_addr),
+                    TrackedMemory::new([0.0; 3], z.clone(), *base_addr),
+                ]),
+                _ => None,
+            },
+            camera_rotation_readable: false,
+            camera_matrix_readable: false,
+            memory: memory.clone(),
+            kind: PositionKind::Position,
+            events: vec![],
+        };
+        handler.saved_slots_changed();
+        handler.selected_position_changed();
+        Some(handler)
     }
 
     pub fn new_look_at_handler(
         address_offsets: &AddressOffsets,
-        arch: &Architecture,
         base_addr: &usize,
-        handle: &ProcessHandle,
+        memory: &Rc<dyn MemoryBackend>,
+        slot_count: &u32,
+        position_store: &PositionStore,
+        fly_config: &FlyConfig,
     ) -> Option<PositionHandler> {
         println!("Loading look at position handler...");
 
-        Some(PositionHandler {
+        let mut handler = PositionHandler {
             active: false,
-            position: TrackedPosition::new(
-                address_offsets.get(&AddressType::XLookAt)?.clone(),
-                address_offsets.get(&AddressType::YLookAt)?.clone(),
-                address_offsets.get(&AddressType::ZLookAt)?.clone(),
-                *arch,
+            position: TrackedVector::new(
+                address_offsets,
+                [AddressType::XLookAt, AddressType::YLookAt, AddressType::ZLookAt],
                 *base_addr,
-            ),
-            saved_position: TrackedPosition::new(
-                address_offsets.get(&AddressType::XLookAt)?.clone(),
-                address_offsets.get(&AddressType::YLookAt)?.clone(),
-                address_offsets.get(&AddressType::ZLookAt)?.clone(),
-                *arch,
-                *base_addr,
-            ),
+            )?,
+            coordinates: [0.0; 3],
+            rotation: None,
+            velocity: None,
+            saved_positions: position_store.load(PositionKind::LookAt.name()),
+            position_store: position_store.clone(),
+            current_slot: "1".to_string(),
+            slot_count: *slot_count,
+            undo_history: VecDeque::new(),
+            redo_history: vec![],
+            flying: false,
+            fly_config: fly_config.clone(),
+            held_inputs: HashSet::new(),
+            fly_velocity: [0.0; 3],
+            last_fly_tick: Instant::now(),
             camera_rotation: None,
-            handle: *handle,
-            name: "look at position".to_string(),
-        })
+            camera_matrix: None,
+            camera_rotation_readable: false,
+            camera_matrix_readable: false,
+            memory: memory.clone(),
+            kind: PositionKind::LookAt,
+            events: vec![],
+        };
+        handler.saved_slots_changed();
+        handler.selected_position_changed();
+        Some(handler)
+    }
+
+    fn store(&mut self, slot: String, label: Option<String>) -> Result<(), Box<dyn Error>> {
+        let [x, y, z] = self.position.fetch_from_game(&*self.memory)?;
+        let mut saved_position = SavedPosition::new(x, y, z, label);
+        saved_position.rotation = fetch_vector(&mut self.rotation, &*self.memory)?;
+        saved_position.velocity = fetch_vector(&mut self.velocity, &*self.memory)?;
+        println!("Stored {} in slot {}! {:}", self.kind, slot, saved_position);
+        self.events.push(Event::PositionStored {
+            handler: self.kind,
+            slot: slot.clone(),
+            position: [saved_position.x, saved_position.y, saved_position.z],
+        });
+        self.saved_positions.insert(slot, saved_position);
+        self.saved_slots_changed();
+        self.selected_position_changed();
+
+        self.position_store.save(self.kind.name(), &self.saved_positions)?;
+        Ok(())
+    }
+
+    fn restore(&mut self, slot: String) -> Result<(), Box<dyn Error>> {
+        let saved_position = self.saved_positions.get(&slot).ok_or_else(|| {
+            HelperError::new("Position", format!("No {} stored in slot {}", self.kind, slot))
+        })?;
+        let saved_position = saved_position.clone();
+        self.record_history()?;
+        self.coordinates = [saved_position.x, saved_position.y, saved_position.z];
+        self.position.apply_to_game(self.coordinates, &*self.memory)?;
+        apply_vector(&mut self.rotation, saved_position.rotation, &*self.memory)?;
+        apply_vector(&mut self.velocity, saved_position.velocity, &*self.memory)?;
+        println!("Restored {} from slot {}! {:}", self.kind, slot, saved_position);
+        self.events.push(Event::PositionRestored {
+            handler: self.kind,
+            slot,
+            position: [saved_position.x, saved_position.y, saved_position.z],
+        });
+        self.teleported(*self.undo_history.back().unwrap());
+        Ok(())
+    }
+
+    fn teleported(&mut self, from: [f32; 3]) {
+        self.events.push(Event::Teleported {
+            handler: self.kind,
+            from,
+            to: self.coordinates,
+        });
+    }
+
+    // Remembers where we are before every write so that it can be undone
+    fn record_history(&mut self) -> io::Result<()> {
+        let current = self.position.fetch_from_game(&*self.memory)?;
+
+        if self.undo_history.len() == TELEPORT_HISTORY_LENGTH {
+            self.undo_history.pop_front();
+        }
+        self.undo_history.push_back(current);
+        self.redo_history.clear();
+        Ok(())
+    }
+
+    fn undo(&mut self) -> Result<(), Box<dyn Error>> {
+        let previous = *self.undo_history.back().ok_or_else(|| {
+            HelperError::new("Position", format!("No {} teleports to undo", self.kind))
+        })?;
+
+        // The history only changes once Lara has moved, so a failed read or write can be retried
+        let current = self.position.fetch_from_game(&*self.memory)?;
+        self.position.apply_to_game(previous, &*self.memory)?;
+        self.undo_history.pop_back();
+        self.redo_history.push(current);
+
+        self.coordinates = previous;
+        println!("Undid {} teleport! {}", self.kind, format_coordinates(self.coordinates));
+        self.teleported(current);
+        Ok(())
+    }
+
+    fn redo(&mut self) -> Result<(), Box<dyn Error>> {
+        let next = *self.redo_history.last().ok_or_else(|| {
+            HelperError::new("Position", format!("No {} teleports to redo", self.kind))
+        })?;
+
+        // The history only changes once Lara has moved, so a failed read or write can be retried
+        let current = self.position.fetch_from_game(&*self.memory)?;
+        self.position.apply_to_game(next, &*self.memory)?;
+        self.redo_history.pop();
+        self.undo_history.push_back(current);
+
+        self.coordinates = next;
+        println!("Redid {} teleport! {}", self.kind, format_coordinates(self.coordinates));
+        self.teleported(current);
+        Ok(())
+    }
+
+    fn toggle_fly_mode(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.flying {
+            self.flying = false;
+            println!("Stopped flying {}", self.kind);
+            return Ok(());
+        }
+
+        if !self.active {
+            self.coordinates = self.position.fetch_from_game(&*self.memory)?;
+            self.active = true;
+        }
+        self.record_history()?;
+        self.flying = true;
+        self.fly_velocity = [0.0; 3];
+        self.last_fly_tick = Instant::now();
+        println!("Started flying {}", self.kind);
+        Ok(())
+    }
+
+    // The camera's look and left vectors from its rotation matrix. Games disagree on whether the
+    // camera looks along its z axis or away from it, so the look vector is taken to be whichever
+    // way agrees with the horizontal forward direction given by the right vector.
+    fn camera_look_vectors(&self) -> Option<([f32; 3], [f32; 3])> {
+        if !self.camera_matrix_readable {
+            return None;
+        }
+        let matrix = self.camera_matrix.as_ref()?;
+        let right = matrix[0].data;
+        let mut forward = matrix[2].data;
+        if forward[0] * -right[1] + forward[1] * right[0] < 0.0 {
+            forward.iter_mut().for_each(|f| *f = -*f);
+        }
+        Some((forward, [-right[0], -right[1], -right[2]]))
+    }
+
+    // Unit vector of the held movement inputs, relative to the camera when it is known
+    fn fly_direction(&self) -> [f32; 3] {
+        let pitched = if self.fly_config.follow_camera_pitch {
+            self.camera_look_vectors()
+        } else {
+            None
+        };
+        let (forward, left) = match (pitched, &self.camera_rotation) {
+            (Some(vectors), _) => vectors,
+            (None, Some((sin, cos))) if self.camera_rotation_readable => {
+                ([-cos.data, sin.data, 0.0], [-sin.data, -cos.data, 0.0])
+            }
+            _ => ([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        };
+
+        let mut direction = [0.0_f32; 3];
+        for input in &self.held_inputs {
+            match input {
+                FlyInput::Forward => (0..3).for_each(|i| direction[i] += forward[i]),
+                FlyInput::Backward => (0..3).for_each(|i| direction[i] -= forward[i]),
+                FlyInput::Left => (0..3).for_each(|i| direction[i] += left[i]),
+                FlyInput::Right => (0..3).for_each(|i| direction[i] -= left[i]),
+                FlyInput::Up => direction[2] += 1.0,
+                FlyInput::Down => direction[2] -= 1.0,
+                FlyInput::Fast | FlyInput::Slow => {}
+            }
+        }
+
+        let length = length(direction);
+        if length > 0.0 {
+            direction.iter_mut().for_each(|d| *d /= length);
+        }
+        direction
+    }
+
+    // Accelerates towards the speed of the held inputs and moves by however far that is over the
+    // real time since the last tick
+    fn fly(&mut self) {
+        let now = Instant::now();
+        let elapsed = now
+            .duration_since(self.last_fly_tick)
+            .as_secs_f32()
+            .min(MAX_FLY_TICK_SECONDS);
+        self.last_fly_tick = now;
+
+        let mut speed = self.fly_config.speed;
+        if self.held_inputs.contains(&FlyInput::Fast) {
+            speed *= self.fly_config.fast_multiplier;
+        }
+        if self.held_inputs.contains(&FlyInput::Slow) {
+            speed *= self.fly_config.slow_multiplier;
+        }
+
+        let direction = self.fly_direction();
+        let mut change = [0.0_f32; 3];
+        for i in 0..3 {
+            change[i] = direction[i] * speed - self.fly_velocity[i];
+        }
+        let max_change = self.fly_config.acceleration * elapsed;
+        let change_length = length(change);
+        if change_length > max_change {
+            change.iter_mut().for_each(|c| *c *= max_change / change_length);
+        }
+
+        for (velocity, change) in self.fly_velocity.iter_mut().zip(change.iter()) {
+            *velocity += change;
+        }
+        for (coordinate, velocity) in self.coordinates.iter_mut().zip(self.fly_velocity.iter()) {
+            *coordinate += velocity * elapsed;
+        }
+    }
+
+    // Numbered slots come first so there are always empty slots to cycle to, followed by any
+    // named slots that have been stored
+    fn slot_names(&self) -> Vec<String> {
+        let mut named = self
+            .saved_positions
+            .keys()
+            .filter(|slot| slot.parse::<u32>().map_or(true, |n| n == 0 || n > self.slot_count))
+            .cloned()
+            .collect::<Vec<_>>();
+        named.sort();
+
+        (1..=self.slot_count)
+            .map(|n| n.to_string())
+            .chain(named)
+            .collect()
+    }
+
+    fn saved_slots_changed(&mut self) {
+        self.events.push(Event::SavedSlotsChanged {
+            handler: self.kind,
+            slots: self.saved_positions.clone(),
+        });
+    }
+
+    // Lets other handlers know where RestorePosition would currently go
+    fn selected_position_changed(&mut self) {
+        self.events.push(Event::SelectedPositionChanged {
+            handler: self.kind,
+            position: self
+                .saved_positions
+                .get(&self.current_slot)
+                .map(|position| [position.x, position.y, position.z]),
+        });
+    }
+
+    fn cycle_slot(&mut self, step: isize) {
+        let slot_names = self.slot_names();
+        if slot_names.is_empty() {
+            return;
+        }
+
+        let current = slot_names
+            .iter()
+            .position(|slot| *slot == self.current_slot)
+            .unwrap_or(0) as isize;
+        let next = (current + step).rem_euclid(slot_names.len() as isize) as usize;
+        self.current_slot = slot_names[next].clone();
+
+        match self.saved_positions.get(&self.current_slot) {
+            Some(position) => println!("Selected {} slot {} {:}", self.kind, self.current_slot, position),
+            None => println!("Selected {} slot {} (empty)", self.kind, self.current_slot),
+        }
+        self.selected_position_changed();
     }
 }
 
 impl Handler for PositionHandler {
+    fn name(&self) -> &str {
+        self.kind.name()
+    }
+
+    fn status(&self) -> String {
+        format!(
+            "{}{}, slot {} selected, {} slots stored",
+            if self.active { "active" } else { "inactive" },
+            if self.flying { " and flying" } else { "" },
+            self.current_slot,
+            self.saved_positions.len()
+        )
+    }
+
+    // The held position would otherwise be written into the game as soon as it restarts
+    fn on_disconnect(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.active {
+            self.active = false;
+            self.flying = false;
+            self.held_inputs.clear();
+            println!("Deactivated {} handler", self.kind);
+        }
+        Ok(())
+    }
+
+    fn rebind(&mut self, base_addr: usize, memory: &Rc<dyn MemoryBackend>) {
+        self.position.rebind(base_addr);
+        for vector in [&mut self.rotation, &mut self.velocity].iter_mut() {
+            if let Some(vector) = vector {
+                vector.rebind(base_addr);
+            }
+        }
+        if let Some((sin, cos)) = &mut self.camera_rotation {
+            sin.rebind(base_addr);
+            cos.rebind(base_addr);
+        }
+        if let Some(camera_matrix) = &mut self.camera_matrix {
+            for value in camera_matrix.iter_mut() {
+                value.rebind(base_addr);
+            }
+        }
+        self.memory = memory.clone();
+    }
+
     fn handle_tick(&mut self) -> Result<(), Box<dyn Error>> {
         if self.active {
-            self.position.apply_to_game(self.handle)?;
-            match &mut self.camera_rotation {
+            // Flying falls back to the sin/cos rotation if the matrix can't be read, and to fixed
+            // directions if neither can, rather than letting go of Lara for the tick
+            let memory = &*self.memory;
+            self.camera_rotation_readable = match &mut self.camera_rotation {
                 Some((sin, cos)) => {
-                    sin.fetch_from_game(self.handle)?;
-                    cos.fetch_from_game(self.handle)?;
+                    sin.fetch_from_game(memory).is_ok() && cos.fetch_from_game(memory).is_ok()
                 }
-                _ => {}
+                None => false,
+            };
+            self.camera_matrix_readable = match &mut self.camera_matrix {
+                Some(matrix) => matrix.iter_mut().all(|row| row.fetch_from_game(memory).is_ok()),
+                None => false,
+            };
+            if self.flying {
+                self.fly();
             }
+            self.position.apply_to_game(self.coordinates, &*self.memory)?;
         }
         Ok(())
     }
     fn handle_action(&mut self, action: Action) -> Result<(), Box<dyn Error>> {
+        match action {
+            Action::Forward { .. }
+            | Action::Backward { .. }
+            | Action::Left { .. }
+            | Action::Right { .. }
+            | Action::Up { .. }
+            | Action::Down { .. }
+                if self.active && !self.flying =>
+            {
+                self.record_history()?
+            }
+            _ => {}
+        }
+
         match action {
             Action::ToggleActive {} => {
                 if self.active {
                     self.active = false;
-                    println!("Deactivated {} handler", self.name);
+                    self.flying = false;
+                    println!("Deactivated {} handler", self.kind);
                 } else {
                     self.active = true;
-                    match self.position.fetch_from_game(self.handle) {
-                        Err(msg) => eprintln!("Error activating {} handler: {}", self.name, msg),
-                        Ok(()) => println!("Activated {} handler", self.name),
+                    match self.position.fetch_from_game(&*self.memory) {
+                        Err(msg) => eprintln!("Error activating {} handler: {}", self.kind, msg),
+                        Ok(coordinates) => {
+                            self.coordinates = coordinates;
+                            println!("Activated {} handler", self.kind)
+                        }
                     }
                 }
             }
-            Action::StorePosition {} => {
-                self.saved_position = self.position.clone();
-                self.saved_position.fetch_from_game(self.handle)?;
-                println!("Stored {}! {:}", self.name, self.saved_position);
+            Action::StorePosition {} => self.store(self.current_slot.clone(), None)?,
+            Action::RestorePosition {} => self.restore(self.current_slot.clone())?,
+            Action::StorePositionSlot { slot, label } => self.store(slot, label)?,
+            // Restores targeted at the other position handler are ignored
+            Action::RestorePositionSlot { slot, handler } if handler.is_none() || handler == Some(self.kind) => {
+                self.restore(slot)?
+            }
+            Action::NextPositionSlot {} => self.cycle_slot(1),
+            Action::PreviousPositionSlot {} => self.cycle_slot(-1),
+            Action::UndoTeleport {} => self.undo()?,
+            Action::RedoTeleport {} => self.redo()?,
+            Action::ToggleFlyMode {} => self.toggle_fly_mode()?,
+            Action::FlyInputPressed { input } => {
+                self.held_inputs.insert(input);
             }
-            Action::RestorePosition {} => {
-                self.position = self.saved_position.clone();
-                self.position.apply_to_game(self.handle)?;
-                println!("Restored {}! {:}", self.name, self.position);
+            Action::FlyInputReleased { input } => {
+                self.held_inputs.remove(&input);
             }
             Action::Forward { distance } => {
-                if self.active {
+                if self.active && !self.flying {
                     match &self.camera_rotation {
                         Some((sin, cos)) => {
-                            self.position.x.data -= distance * cos.data;
-                            self.position.y.data += distance * sin.data;
+                            self.coordinates[0] -= distance * cos.data;
+                            self.coordinates[1] += distance * sin.data;
                         }
-                        _ => self.position.x.data += distance,
+                        _ => self.coordinates[0] += distance,
                     }
                 }
             }
             Action::Backward { distance } => {
-                if self.active {
+                if self.active && !self.flying {
                     match &self.camera_rotation {
                         Some((sin, cos)) => {
-                            self.position.x.data += distance * cos.data;
-                            self.position.y.data -= distance * sin.data;
+                            self.coordinates[0] += distance * cos.data;
+                            self.coordinates[1] -= distance * sin.data;
                         }
-                        _ => self.position.x.data -= distance,
+                        _ => self.coordinates[0] -= distance,
                     }
                 }
             }
             Action::Left { distance } => {
-                if self.active {
+                if self.active && !self.flying {
                     match &self.camera_rotation {
                         Some((sin, cos)) => {
-                            self.position.x.data -= distance * sin.data;
-                            self.position.y.data -= distance * cos.data;
+                            self.coordinates[0] -= distance * sin.data;
+                            self.coordinates[1] -= distance * cos.data;
                         }
-                        _ => self.position.y.data += distance,
+                        _ => self.coordinates[1] += distance,
                     }
                 }
             }
             Action::Right { distance } => {
-                if self.active {
+                if self.active && !self.flying {
                     match &self.camera_rotation {
                         Some((sin, cos)) => {
-                            self.position.x.data += distance * sin.data;
-                            self.position.y.data += distance * cos.data;
+                            self.coordinates[0] += distance * sin.data;
+                            self.coordinates[1] += distance * cos.data;
                         }
-                        _ => self.position.y.data -= distance,
+                        _ => self.coordinates[1] -= distance,
                     }
                 }
             }
             Action::Up { distance } => {
-                if self.active {
-                    self.position.z.data += distance;
+                if self.active && !self.flying {
+                    self.coordinates[2] += distance;
                 }
             }
             Action::Down { distance } => {
-                if self.active {
-                    self.position.z.data -= distance;
+                if self.active && !self.flying {
+                    self.coordinates[2] -= distance;
                 }
             }
             _ => (),
         }
         Ok(())
     }
+
+    fn take_events(&mut self) -> Vec<Event> {
+        self.events.drain(..).collect()
+    }
 }
 
-#[derive(Debug, Clone)]
-struct TrackedPosition {
-    x: TrackedMemory<f32>,
-    y: TrackedMemory<f32>,
-    z: TrackedMemory<f32>,
+fn length(vector: [f32; 3]) -> f32 {
+    vector.iter().map(|v| v * v).sum::<f32>().sqrt()
 }
 
-impl TrackedPosition {
-    fn fetch_from_game(&mut self, handle: ProcessHandle) -> io::Result<()> {
-        self.x.fetch_from_game(handle)?;
-        self.y.fetch_from_game(handle)?;
-        self.z.fetch_from_game(handle)?;
-        Ok(())
+fn fetch_vector(vector: &mut Option<TrackedVector>, memory: &dyn MemoryBackend) -> io::Result<Option<[f32; 3]>> {
+    vector.as_mut().map(|vector| vector.fetch_from_game(memory)).transpose()
+}
+
+// Only writes anything if the version has the addresses and the value was saved, so positions
+// stored before the addresses were known still restore
+fn apply_vector(
+    vector: &mut Option<TrackedVector>,
+    values: Option<[f32; 3]>,
+    memory: &dyn MemoryBackend,
+) -> io::Result<()> {
+    if let (Some(vector), Some(values)) = (vector, values) {
+        vector.apply_to_game(values, memory)?;
     }
+    Ok(())
+}
 
-    fn apply_to_game(&mut self, handle: ProcessHandle) -> io::Result<()> {
-        self.x.apply_to_game(handle)?;
-        self.y.apply_to_game(handle)?;
-        self.z.apply_to_game(handle)?;
-        Ok(())
+fn format_coordinates([x, y, z]: [f32; 3]) -> String {
+    format!("({}, {}, {})", x, y, z)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_backend::InMemoryBackend;
+    use process_memory::Architecture;
+    use std::{env, fs, process};
+
+    // Lara's coordinates are reached through a pointer, as in the games
+    const LARA_POINTER: usize = 0x100;
+    const LARA: usize = 0x2000;
+    const X: usize = LARA;
+    const Y: usize = LARA + 0x4;
+    const Z: usize = LARA + 0x8;
+
+    // A saved positions file of its own for each test, removed when the test finishes
+    struct TestStore {
+        path: String,
+    }
+
+    impl TestStore {
+        fn new(name: &str) -> TestStore {
+            let path = env::temp_dir().join(format!("tomb-helper-test-{}-{}.json", process::id(), name));
+            TestStore {
+                path: path.to_str().unwrap().to_string(),
+            }
+        }
+
+        fn store(&self) -> PositionStore {
+            PositionStore::new(&self.path, &"Tomb Raider".to_string(), &"1.0".to_string())
+        }
     }
 
-    fn new(
-        x_offsets: Vec<usize>,
-        y_offsets: Vec<usize>,
-        z_offsets: Vec<usize>,
-        arch: Architecture,
-        base_addr: usize,
-    ) -> TrackedPosition {
-        TrackedPosition {
-            x: TrackedMemory::new(0.0, x_offsets, arch, base_addr),
-            y: TrackedMemory::new(0.0, y_offsets, arch, base_addr),
-            z: TrackedMemory::new(0.0, z_offsets, arch, base_addr),
+    impl Drop for TestStore {
+        fn drop(&mut self) {
+            fs::remove_file(&self.path).ok();
         }
     }
-}
 
-impl fmt::Display for TrackedPosition {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "({}, {}, {})", self.x.data, self.y.data, self.z.data)
+    fn position_handler(backend: &Rc<InMemoryBackend>, store: &TestStore) -> PositionHandler {
+        position_handler_with(backend, store, vec![])
+    }
+
+    fn position_handler_with(
+        backend: &Rc<InMemoryBackend>,
+        store: &TestStore,
+        other_offsets: Vec<(AddressType, Vec<usize>)>,
+    ) -> PositionHandler {
+        let address_offsets: AddressOffsets = vec![
+            (AddressType::XPosition, vec![LARA_POINTER, X - LARA]),
+            (AddressType::YPosition, vec![LARA_POINTER, Y - LARA]),
+            (AddressType::ZPosition, vec![LARA_POINTER, Z - LARA]),
+        ]
+        .into_iter()
+        .chain(other_offsets)
+        .collect();
+        backend.set_pointer(LARA_POINTER, LARA);
+        let memory: Rc<dyn MemoryBackend> = backend.clone();
+        PositionHandler::new_position_handler(
+            &address_offsets,
+            &0,
+            &memory,
+            &10,
+            &store.store(),
+            &FlyConfig::default(),
+        )
+        .unwrap()
+    }
+
+    fn move_lara(backend: &InMemoryBackend, [x, y, z]: [f32; 3]) {
+        backend.set(X, x);
+        backend.set(Y, y);
+        backend.set(Z, z);
+    }
+
+    fn lara_position(backend: &InMemoryBackend) -> [f32; 3] {
+        [
+            backend.get(X).unwrap(),
+            backend.get(Y).unwrap(),
+            backend.get(Z).unwrap(),
+        ]
+    }
+
+    fn store_slot(slot: &str) -> Action {
+        Action::StorePositionSlot {
+            slot: slot.to_string(),
+            label: None,
+        }
+    }
+
+    fn restore_slot(slot: &str) -> Action {
+        Action::RestorePositionSlot {
+            slot: slot.to_string(),
+            handler: None,
+        }
+    }
+
+    #[test]
+    fn restores_stored_slots() {
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let store = TestStore::new("restores_stored_slots");
+        let mut handler = position_handler(&backend, &store);
+
+        move_lara(&backend, [1.0, 2.0, 3.0]);
+        handler.handle_action(store_slot("1")).unwrap();
+        move_lara(&backend, [4.0, 5.0, 6.0]);
+        handler.handle_action(store_slot("pit")).unwrap();
+
+        move_lara(&backend, [7.0, 8.0, 9.0]);
+        handler.handle_action(restore_slot("1")).unwrap();
+        assert_eq!(lara_position(&backend), [1.0, 2.0, 3.0]);
+        handler.handle_action(restore_slot("pit")).unwrap();
+        assert_eq!(lara_position(&backend), [4.0, 5.0, 6.0]);
+
+        assert!(handler.take_events().iter().any(|event| matches!(
+            event,
+            Event::PositionRestored { slot, position, .. } if slot == "pit" && *position == [4.0, 5.0, 6.0]
+        )));
+    }
+
+    #[test]
+    fn stored_slots_are_loaded_again() {
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let store = TestStore::new("stored_slots_are_loaded_again");
+        let mut handler = position_handler(&backend, &store);
+        move_lara(&backend, [1.0, 2.0, 3.0]);
+        handler.handle_action(store_slot("pit")).unwrap();
+
+        let mut reloaded = position_handler(&backend, &store);
+        move_lara(&backend, [7.0, 8.0, 9.0]);
+        reloaded.handle_action(restore_slot("pit")).unwrap();
+        assert_eq!(lara_position(&backend), [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn restoring_can_be_undone_and_redone() {
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let store = TestStore::new("restoring_can_be_undone_and_redone");
+        let mut handler = position_handler(&backend, &store);
+        move_lara(&backend, [1.0, 2.0, 3.0]);
+        handler.handle_action(store_slot("1")).unwrap();
+
+        move_lara(&backend, [7.0, 8.0, 9.0]);
+        handler.handle_action(restore_slot("1")).unwrap();
+        handler.handle_action(Action::UndoTeleport {}).unwrap();
+        assert_eq!(lara_position(&backend), [7.0, 8.0, 9.0]);
+        handler.handle_action(Action::RedoTeleport {}).unwrap();
+        assert_eq!(lara_position(&backend), [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn restoring_an_empty_slot_leaves_lara_alone() {
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let store = TestStore::new("restoring_an_empty_slot_leaves_lara_alone");
+        let mut handler = position_handler(&backend, &store);
+        move_lara(&backend, [7.0, 8.0, 9.0]);
+
+        assert!(handler.handle_action(restore_slot("3")).is_err());
+        assert_eq!(lara_position(&backend), [7.0, 8.0, 9.0]);
+    }
+
+    #[test]
+    fn holds_the_position_while_active() {
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let store = TestStore::new("holds_the_position_while_active");
+        let mut handler = position_handler(&backend, &store);
+        move_lara(&backend, [1.0, 2.0, 3.0]);
+
+        handler.handle_action(Action::ToggleActive {}).unwrap();
+        move_lara(&backend, [1.0, 2.0, -50.0]);
+        handler.handle_tick().unwrap();
+        assert_eq!(lara_position(&backend), [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn keeps_flying_when_the_camera_matrix_is_unreadable() {
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let store = TestStore::new("keeps_flying_when_the_camera_matrix_is_unreadable");
+        // Nothing is ever written at the camera matrix, so reading it always fails
+        let mut handler = position_handler_with(
+            &backend,
+            &store,
+            vec![
+                (AddressType::CameraRotationX, vec![0x3000]),
+                (AddressType::CameraRotationY, vec![0x300c]),
+                (AddressType::CameraRotationZ, vec![0x3018]),
+            ],
+        );
+        move_lara(&backend, [1.0, 2.0, 3.0]);
+
+        handler.handle_action(Action::ToggleFlyMode {}).unwrap();
+        move_lara(&backend, [1.0, 2.0, -50.0]);
+        handler.handle_tick().unwrap();
+        assert_eq!(lara_position(&backend), [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn keeps_holding_lara_when_the_camera_rotation_is_unreadable() {
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let store = TestStore::new("keeps_holding_lara_when_the_camera_rotation_is_unreadable");
+        // Nothing is ever written at the camera rotation, so reading it always fails
+        let mut handler = position_handler_with(
+            &backend,
+            &store,
+            vec![
+                (AddressType::CameraSin, vec![0x3000]),
+                (AddressType::CameraCos, vec![0x3004]),
+            ],
+        );
+        move_lara(&backend, [1.0, 2.0, 3.0]);
+
+        handler.handle_action(Action::ToggleActive {}).unwrap();
+        move_lara(&backend, [1.0, 2.0, -50.0]);
+        handler.handle_tick().unwrap();
+        assert_eq!(lara_position(&backend), [1.0, 2.0, 3.0]);
+
+        handler.handle_action(Action::ToggleFlyMode {}).unwrap();
+        move_lara(&backend, [1.0, 2.0, -50.0]);
+        handler.handle_tick().unwrap();
+        assert_eq!(lara_position(&backend), [1.0, 2.0, 3.0]);
+    }
+
+    #[test]
+    fn failed_undo_and_redo_keep_the_history() {
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let store = TestStore::new("failed_undo_and_redo_keep_the_history");
+        let mut handler = position_handler(&backend, &store);
+        move_lara(&backend, [1.0, 2.0, 3.0]);
+        handler.handle_action(store_slot("1")).unwrap();
+        move_lara(&backend, [7.0, 8.0, 9.0]);
+        handler.handle_action(restore_slot("1")).unwrap();
+
+        // Lara's pointer leads nowhere, as it can during loading screens
+        backend.set_pointer(LARA_POINTER, 0x9000);
+        assert!(handler.handle_action(Action::UndoTeleport {}).is_err());
+        backend.set_pointer(LARA_POINTER, LARA);
+        handler.handle_action(Action::UndoTeleport {}).unwrap();
+        assert_eq!(lara_position(&backend), [7.0, 8.0, 9.0]);
+
+        backend.set_pointer(LARA_POINTER, 0x9000);
+        assert!(handler.handle_action(Action::RedoTeleport {}).is_err());
+        backend.set_pointer(LARA_POINTER, LARA);
+        handler.handle_action(Action::RedoTeleport {}).unwrap();
+        assert_eq!(lara_position(&backend), [1.0, 2.0, 3.0]);
     }
 }
@@ -1,4 +1,9 @@
+use crate::error::HelperError;
+use crate::readable_from_path::ReadableFromPath;
+use crate::version_fingerprint::VersionFingerprint;
 use process_memory::Architecture;
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
 use std::collections::HashMap;
 
 pub fn known_process_details() -> Vec<ProcessDetails> {
@@ -9,6 +14,7 @@ pub fn known_process_details() -> Vec<ProcessDetails> {
             VersionIdentifier {
                 version: String::from("v1.01.748.0"),
                 image_size: None,
+                ..Default::default()
             },
             Architecture::Arch32Bit,
             vec![
@@ -29,6 +35,7 @@ pub fn known_process_details() -> Vec<ProcessDetails> {
             VersionIdentifier {
                 version: String::from("v1.1 build 838.0_32"),
                 image_size: None,
+                ..Default::default()
             },
             Architecture::Arch32Bit,
             vec![
@@ -49,6 +56,7 @@ pub fn known_process_details() -> Vec<ProcessDetails> {
             VersionIdentifier {
                 version: String::from("(Steam) v1.01.0.0"),
                 image_size: Some(38543360),
+                ..Default::default()
             },
             Architecture::Arch32Bit,
             vec![
@@ -69,6 +77,7 @@ pub fn known_process_details() -> Vec<ProcessDetails> {
             VersionIdentifier {
                 version: String::from("(Epic Games) v1.01.0.0"),
                 image_size: Some(38535168),
+                ..Default::default()
             },
             Architecture::Arch32Bit,
             vec![
@@ -89,6 +98,7 @@ pub fn known_process_details() -> Vec<ProcessDetails> {
             VersionIdentifier {
                 version: String::from("v1.0 build 820.0_64"),
                 image_size: None,
+                ..Default::default()
             },
             Architecture::Arch64Bit,
             vec![
@@ -112,6 +122,7 @@ pub fn known_process_details() -> Vec<ProcessDetails> {
             VersionIdentifier {
                 version: String::from("v1.0 build 1027.0_64"),
                 image_size: None,
+                ..Default::default()
             },
             Architecture::Arch64Bit,
             vec![
@@ -132,6 +143,7 @@ pub fn known_process_details() -> Vec<ProcessDetails> {
             VersionIdentifier {
                 version: String::from("(Steam) v1.0 build 0.0_64"),
                 image_size: Some(58654720),
+                ..Default::default()
             },
             Architecture::Arch64Bit,
             vec![
@@ -152,6 +164,7 @@ pub fn known_process_details() -> Vec<ProcessDetails> {
             VersionIdentifier {
                 version: String::from("(Epic Games) v1.0 build 1027.0_64"),
                 image_size: Some(57122816),
+                ..Default::default()
             },
             Architecture::Arch64Bit,
             vec![
@@ -172,6 +185,7 @@ pub fn known_process_details() -> Vec<ProcessDetails> {
             VersionIdentifier {
                 version: String::from("v1.0 build 234.1_64"),
                 image_size: None,
+                ..Default::default()
             },
             Architecture::Arch64Bit,
             vec![
@@ -206,6 +220,7 @@ pub fn known_process_details() -> Vec<ProcessDetails> {
             VersionIdentifier {
                 version: String::from("v1.0 build 298.0_64"),
                 image_size: None,
+                ..Default::default()
             },
             Architecture::Arch64Bit,
             vec![
@@ -235,6 +250,7 @@ pub fn known_process_details() -> Vec<ProcessDetails> {
             VersionIdentifier {
                 version: String::from("v1.0 build 458.0_64"),
                 image_size: None,
+                ..Default::default()
             },
             Architecture::Arch64Bit,
             vec![
@@ -263,6 +279,7 @@ pub fn known_process_details() -> Vec<ProcessDetails> {
             VersionIdentifier {
                 version: String::from("v1.0 build 489.0_64"),
                 image_size: None,
+                ..Default::default()
             },
             Architecture::Arch64Bit,
             vec![
@@ -291,6 +308,7 @@ pub fn known_process_details() -> Vec<ProcessDetails> {
             VersionIdentifier {
                 version: String::from("(Epic Games) v1.0 build 492.0_64"),
                 image_size: Some(72794112),
+                ..Default::default()
             },
             Architecture::Arch64Bit,
             vec![
@@ -317,6 +335,7 @@ pub fn known_process_details() -> Vec<ProcessDetails> {
             VersionIdentifier {
                 version: String::from("(Steam) v1.0 build 492.0_64"),
                 image_size: Some(73662464),
+                ..Default::default()
             },
             Architecture::Arch64Bit,
             vec![
@@ -340,21 +359,33 @@ pub fn known_process_details() -> Vec<ProcessDetails> {
     ]
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Deserialize)]
 pub enum AddressType {
     Version,
     XPosition,
     YPosition,
     ZPosition,
+    // Lara's rotation about each axis, stored and restored along with her position
+    XRotation,
+    YRotation,
+    ZRotation,
+    XVelocity,
+    YVelocity,
+    ZVelocity,
     XLookAt,
     YLookAt,
     ZLookAt,
-    // TODO: Work out a more sensible way of representing the camera matrix
     // Note: The easiest way to find these values is to search for camera XYZ values which form the
     // last entries of the extrinsic camera matrix (https://ksimek.github.io/2012/08/22/extrinsic/)
     // From there the first 2 entries of the matrix can be used as CameraSin and CameraCos
     CameraSin,
     CameraCos,
+    // The rows of the rotation part of the same matrix, each pointing at 3 consecutive floats.
+    // CameraRotationX is the camera's right vector (starting with CameraSin and CameraCos),
+    // CameraRotationY its up vector and CameraRotationZ its look vector.
+    CameraRotationX,
+    CameraRotationY,
+    CameraRotationZ,
     CutscenePrompt,
     CutsceneStatus,
     CutsceneTimeline,
@@ -364,22 +395,114 @@ pub enum AddressType {
 
 pub type AddressOffsets = HashMap<AddressType, Vec<usize>>;
 
-#[derive(Debug, Clone)]
+// How to turn the location of a signature match into the address the pointer chain starts from
+#[derive(Debug, Clone, Deserialize)]
+pub enum SignatureRule {
+    // The match location itself, plus an offset
+    Match { offset: isize },
+    // A 32 bit absolute address stored in the matched instruction (32 bit games)
+    Absolute { operand_offset: usize },
+    // A 32 bit displacement relative to the end of the matched instruction (64 bit games)
+    RipRelative {
+        operand_offset: usize,
+        instruction_length: usize,
+    },
+}
+
+impl Default for SignatureRule {
+    fn default() -> Self {
+        SignatureRule::Match { offset: 0 }
+    }
+}
+
+// An array of bytes pattern such as "48 8B 05 ?? ?? ?? ?? 48 85 C0" which is searched for in the
+// module image. The address found by the rule is followed by `offsets` like any other chain.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Signature {
+    pub pattern: String,
+    #[serde(default)]
+    pub rule: SignatureRule,
+    #[serde(default, deserialize_with = "deserialize_offsets")]
+    pub offsets: Vec<usize>,
+}
+
+impl Signature {
+    pub fn parse_pattern(&self) -> Result<Vec<Option<u8>>, HelperError> {
+        self.pattern
+            .split_whitespace()
+            .map(|byte| match byte {
+                "?" | "??" => Ok(None),
+                _ => u8::from_str_radix(byte, 16).map(Some).map_err(|_| {
+                    HelperError::new(
+                        "Version table",
+                        format!("invalid byte {:?} in signature {:?}", byte, self.pattern),
+                    )
+                }),
+            })
+            .collect()
+    }
+}
+
+pub type AddressSignatures = HashMap<AddressType, Signature>;
+
+// If any of the PE fingerprint fields are given then all of them must match the running game,
+// otherwise the version string is read from the Version address.
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct VersionIdentifier {
     // Version string
     pub version: String,
     // Size of the image in memory. Used to differentiate between Steam and Epic Games versions since they share the same version string
     // https://learn.microsoft.com/en-us/windows/win32/debug/pe-format#optional-header-windows-specific-fields-image-only
+    #[serde(default)]
     pub image_size: Option<usize>,
+    // TimeDateStamp from the COFF file header, the time the executable was linked
+    #[serde(default)]
+    pub timestamp: Option<u32>,
+    // CheckSum from the optional header
+    #[serde(default)]
+    pub checksum: Option<u32>,
+    // Hash of the whole section table, see VersionFingerprint
+    #[serde(default)]
+    pub section_table_hash: Option<u64>,
+    // Hashes of individual section headers by section name
+    #[serde(default)]
+    pub section_hashes: HashMap<String, u64>,
+}
+
+impl VersionIdentifier {
+    pub fn has_fingerprint(&self) -> bool {
+        self.image_size.is_some()
+            || self.timestamp.is_some()
+            || self.checksum.is_some()
+            || self.section_table_hash.is_some()
+            || !self.section_hashes.is_empty()
+    }
+
+    pub fn matches_fingerprint(&self, fingerprint: &VersionFingerprint) -> bool {
+        self.image_size.map_or(true, |v| v == fingerprint.image_size)
+            && self.timestamp.map_or(true, |v| v == fingerprint.timestamp)
+            && self.checksum.map_or(true, |v| v == fingerprint.checksum)
+            && self.section_table_hash.map_or(true, |v| v == fingerprint.section_table_hash)
+            && self.section_hashes.iter().all(|(name, hash)| {
+                fingerprint
+                    .sections
+                    .iter()
+                    .any(|section| &section.name == name && section.hash == *hash)
+            })
+    }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Deserialize)]
 pub struct ProcessDetails {
     pub name: String,
     pub executable_name: String,
     pub version: VersionIdentifier,
+    #[serde(deserialize_with = "deserialize_arch")]
     pub arch: Architecture,
+    #[serde(default, deserialize_with = "deserialize_address_offsets")]
     pub address_offsets: AddressOffsets,
+    #[serde(default)]
+    pub signatures: AddressSignatures,
 }
 
 impl ProcessDetails {
@@ -396,6 +519,436 @@ impl ProcessDetails {
             version,
             arch,
             address_offsets,
+            signatures: HashMap::new(),
+        }
+    }
+
+    fn validate(&self) -> Result<(), HelperError> {
+        if self.name.is_empty() || self.executable_name.is_empty() || self.version.version.is_empty() {
+            return Err(HelperError::new(
+                "Version table",
+                "name, executable_name and version must not be empty",
+            ));
+        }
+
+        for (address_type, offsets) in &self.address_offsets {
+            if offsets.is_empty() {
+                return Err(HelperError::new(
+                    "Version table",
+                    format!("{:?} has an empty pointer chain", address_type),
+                ));
+            }
+        }
+
+        // Entries that can't be identified are still useful as a source of signatures for
+        // unknown versions
+        if !self.version.has_fingerprint()
+            && !self.address_offsets.contains_key(&AddressType::Version)
+            && self.signatures.is_empty()
+        {
+            return Err(HelperError::new(
+                "Version table",
+                "needs a fingerprint, a Version address or signatures to be used",
+            ));
+        }
+
+        for (address_type, signature) in &self.signatures {
+            let pattern = signature.parse_pattern()?;
+            if pattern.iter().all(|byte| byte.is_none()) {
+                return Err(HelperError::new(
+                    "Version table",
+                    format!("signature for {:?} has no fixed bytes", address_type),
+                ));
+            }
+
+            let operand_end = match signature.rule {
+                SignatureRule::Match { .. } => 0,
+                SignatureRule::Absolute { operand_offset } => operand_offset + 4,
+                SignatureRule::RipRelative { operand_offset, .. } => operand_offset + 4,
+            };
+            if operand_end > pattern.len() {
+                return Err(HelperError::new(
+                    "Version table",
+                    format!("signature operand for {:?} is outside of the pattern", address_type),
+                ));
+            }
+        }
+
+        let groups = [
+            vec![AddressType::XPosition, AddressType::YPosition, AddressType::ZPosition],
+            vec![AddressType::XRotation, AddressType::YRotation, AddressType::ZRotation],
+            vec![AddressType::XVelocity, AddressType::YVelocity, AddressType::ZVelocity],
+            vec![AddressType::XLookAt, AddressType::YLookAt, AddressType::ZLookAt],
+            vec![AddressType::CameraSin, AddressType::CameraCos],
+            vec![
+                AddressType::CameraRotationX,
+                AddressType::CameraRotationY,
+                AddressType::CameraRotationZ,
+            ],
+        ];
+        for group in groups.iter() {
+            let present = group.iter().filter(|t| self.address_offsets.contains_key(t)).count();
+            if present != 0 && present != group.len() {
+                return Err(HelperError::new(
+                    "Version table",
+                    format!("{:?} must either all be given or all be omitted", group),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// The version table is read as raw values so that a single bad entry can be reported by name
+// and skipped rather than failing the whole file.
+pub type VersionTable = Vec<serde_json::Value>;
+
+impl ReadableFromPath for VersionTable {}
+
+pub fn load_process_details(version_table_location: &Option<String>) -> Vec<ProcessDetails> {
+    let mut process_details = known_process_details();
+
+    let location = match version_table_location {
+        Some(location) => location,
+        None => return process_details,
+    };
+
+    // A table that can't be read at all shouldn't stop the built-in versions from working
+    let version_table = match VersionTable::try_from_path(location, &String::from("game version table")) {
+        Ok(version_table) => version_table,
+        Err(msg) => {
+            eprintln!("Error: Ignoring game version table {}: {}", location, msg);
+            return process_details;
+        }
+    };
+    for (index, value) in version_table.into_iter().enumerate() {
+        let description = format!(
+            "entry {} ({} {})",
+            index,
+            value["name"].as_str().unwrap_or("<no name>"),
+            value["version"]["version"].as_str().unwrap_or("<no version>"),
+        );
+
+        let details = serde_json::from_value::<ProcessDetails>(value)
+            .map_err(|e| HelperError::new("Version table", e.to_string()))
+            .and_then(|details| details.validate().map(|_| details));
+
+        match details {
+            Err(msg) => eprintln!("Error: Skipping game version table {}: {}", description, msg),
+            Ok(details) => {
+                let existing = process_details.iter_mut().find(|d| {
+                    d.executable_name == details.executable_name
+                        && d.version.version == details.version.version
+                        && d.version.image_size == details.version.image_size
+                        && d.version.timestamp == details.version.timestamp
+                        && d.version.checksum == details.version.checksum
+                        && d.version.section_table_hash == details.version.section_table_hash
+                        && d.version.section_hashes == details.version.section_hashes
+                });
+                match existing {
+                    Some(existing) => *existing = details,
+                    None => process_details.push(details),
+                }
+            }
+        }
+    }
+
+    process_details
+}
+
+fn deserialize_arch<'de, D>(deserializer: D) -> Result<Architecture, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    match String::deserialize(deserializer)?.as_str() {
+        "Arch32Bit" => Ok(Architecture::Arch32Bit),
+        "Arch64Bit" => Ok(Architecture::Arch64Bit),
+        other => Err(de::Error::custom(format!(
+            "unknown arch {:?}, expected \"Arch32Bit\" or \"Arch64Bit\"",
+            other
+        ))),
+    }
+}
+
+// Offsets may be given as plain numbers or as "0x"-prefixed hex strings (underscores allowed)
+// so they can be copied from Cheat Engine or this file.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum OffsetValue {
+    Number(usize),
+    Text(String),
+}
+
+fn parse_offsets<E: de::Error>(offsets: Vec<OffsetValue>) -> Result<Vec<usize>, E> {
+    offsets
+        .into_iter()
+        .map(|offset| match offset {
+            OffsetValue::Number(n) => Ok(n),
+            OffsetValue::Text(text) => {
+                let digits = text.replace('_', "");
+                let parsed = match digits.strip_prefix("0x") {
+                    Some(hex) => usize::from_str_radix(hex, 16),
+                    None => digits.parse(),
+                };
+                parsed.map_err(|_| de::Error::custom(format!("invalid offset {:?}", text)))
+            }
+        })
+        .collect()
+}
+
+fn deserialize_offsets<'de, D>(deserializer: D) -> Result<Vec<usize>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    parse_offsets(Vec::<OffsetValue>::deserialize(deserializer)?)
+}
+
+fn deserialize_address_offsets<'de, D>(deserializer: D) -> Result<AddressOffsets, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    HashMap::<AddressType, Vec<OffsetValue>>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(address_type, offsets)| Ok((address_type, parse_offsets(offsets)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::version_fingerprint::SectionFingerprint;
+    use serde_json::json;
+    use std::{env, fs, process};
+
+    fn fingerprint() -> VersionFingerprint {
+        VersionFingerprint {
+            image_size: 0x2a0_0000,
+            timestamp: 1_600_000_000,
+            checksum: 0x1234,
+            sections: vec![
+                SectionFingerprint {
+                    name: ".text".to_string(),
+                    hash: 11,
+                },
+                SectionFingerprint {
+                    name: ".data".to_string(),
+                    hash: 22,
+                },
+            ],
+            section_table_hash: 33,
+        }
+    }
+
+    fn version() -> VersionIdentifier {
+        VersionIdentifier {
+            version: "1.0".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn matches_when_every_given_field_matches() {
+        assert!(!version().has_fingerprint());
+
+        let version = VersionIdentifier {
+            image_size: Some(0x2a0_0000),
+            checksum: Some(0x1234),
+            section_hashes: vec![(".text".to_string(), 11)].into_iter().collect(),
+            ..version()
+        };
+        assert!(version.has_fingerprint());
+        assert!(version.matches_fingerprint(&fingerprint()));
+    }
+
+    #[test]
+    fn does_not_match_when_any_given_field_differs() {
+        let mismatches = vec![
+            VersionIdentifier {
+                image_size: Some(0x2b0_0000),
+                ..version()
+            },
+            VersionIdentifier {
+                timestamp: Some(1_600_000_001),
+                ..version()
+            },
+            VersionIdentifier {
+                checksum: Some(0x4321),
+                ..version()
+            },
+            VersionIdentifier {
+                section_table_hash: Some(34),
+                ..version()
+            },
+            VersionIdentifier {
+                section_hashes: vec![(".data".to_string(), 11)].into_iter().collect(),
+                ..version()
+            },
+            VersionIdentifier {
+                section_hashes: vec![(".rdata".to_string(), 11)].into_iter().collect(),
+                ..version()
+            },
+        ];
+        for version in mismatches {
+            assert!(!version.matches_fingerprint(&fingerprint()), "{:?} matched", version);
+        }
+    }
+
+    // A version table file of its own for each test, removed when the test finishes
+    struct TestVersionTable {
+        path: String,
+    }
+
+    impl TestVersionTable {
+        fn new(name: &str, content: &str) -> TestVersionTable {
+            let path = env::temp_dir().join(format!("tomb-helper-test-{}-{}.json", process::id(), name));
+            fs::write(&path, content).unwrap();
+            TestVersionTable {
+                path: path.to_str().unwrap().to_string(),
+            }
         }
+
+        fn load(&self) -> Vec<ProcessDetails> {
+            load_process_details(&Some(self.path.clone()))
+        }
+    }
+
+    impl Drop for TestVersionTable {
+        fn drop(&mut self) {
+            fs::remove_file(&self.path).ok();
+        }
+    }
+
+    fn details(value: serde_json::Value) -> ProcessDetails {
+        serde_json::from_value(value).unwrap()
+    }
+
+    fn validation_error(value: serde_json::Value) -> String {
+        details(value).validate().unwrap_err().to_string()
+    }
+
+    #[test]
+    fn offsets_can_be_given_in_hex() {
+        let details = details(json!({
+            "name": "Tomb Raider",
+            "executable_name": "tomb.exe",
+            "version": {"version": "1.0"},
+            "arch": "Arch64Bit",
+            "address_offsets": {
+                "Version": [4096],
+                "XPosition": ["0x01_0E_65_98", "0x10"],
+                "YPosition": ["1_024", 20],
+            }
+        }));
+        assert_eq!(details.address_offsets[&AddressType::Version], vec![4096]);
+        assert_eq!(details.address_offsets[&AddressType::XPosition], vec![0x010E_6598, 0x10]);
+        assert_eq!(details.address_offsets[&AddressType::YPosition], vec![1024, 20]);
+
+        for offset in &["0xZZ", "ten", "-1"] {
+            let value = json!({
+                "name": "Tomb Raider",
+                "executable_name": "tomb.exe",
+                "version": {"version": "1.0"},
+                "arch": "Arch64Bit",
+                "address_offsets": {"Version": [offset]}
+            });
+            let error = serde_json::from_value::<ProcessDetails>(value).unwrap_err().to_string();
+            assert!(error.contains("invalid offset"), "{} gave {}", offset, error);
+        }
+    }
+
+    #[test]
+    fn validation_explains_what_is_wrong() {
+        let valid = json!({
+            "name": "Tomb Raider",
+            "executable_name": "tomb.exe",
+            "version": {"version": "1.0"},
+            "arch": "Arch64Bit",
+            "address_offsets": {"Version": ["0x1000"]}
+        });
+        assert!(details(valid.clone()).validate().is_ok());
+
+        let mut unnamed = valid.clone();
+        unnamed["name"] = json!("");
+        assert_eq!(
+            validation_error(unnamed),
+            "Version table Error: name, executable_name and version must not be empty"
+        );
+
+        let mut unidentifiable = valid.clone();
+        unidentifiable["address_offsets"] = json!({});
+        assert_eq!(
+            validation_error(unidentifiable),
+            "Version table Error: needs a fingerprint, a Version address or signatures to be used"
+        );
+
+        let mut empty_chain = valid.clone();
+        empty_chain["address_offsets"]["XPosition"] = json!([]);
+        assert_eq!(
+            validation_error(empty_chain),
+            "Version table Error: XPosition has an empty pointer chain"
+        );
+
+        let mut partial_position = valid;
+        partial_position["address_offsets"]["XPosition"] = json!([0]);
+        assert_eq!(
+            validation_error(partial_position),
+            "Version table Error: [XPosition, YPosition, ZPosition] must either all be given or all be omitted"
+        );
+    }
+
+    #[test]
+    fn version_table_entries_replace_built_in_versions_with_the_same_identity() {
+        let known = known_process_details();
+        let table = TestVersionTable::new(
+            "version_table_entries_replace_built_in_versions_with_the_same_identity",
+            &json!([
+                {
+                    "name": "Tomb Raider 2013",
+                    "executable_name": "TombRaider.exe",
+                    "version": {"version": "v1.01.748.0"},
+                    "arch": "Arch32Bit",
+                    "address_offsets": {"Version": ["0x1000"]}
+                },
+                {
+                    "name": "Tomb Raider 2013",
+                    "executable_name": "TombRaider.exe",
+                    "version": {"version": "v1.01.748.0", "image_size": 1234},
+                    "arch": "Arch32Bit",
+                    "address_offsets": {"Version": ["0x2000"]}
+                },
+                {
+                    "name": "",
+                    "executable_name": "TombRaider.exe",
+                    "version": {"version": "v9"},
+                    "arch": "Arch32Bit"
+                }
+            ])
+            .to_string(),
+        );
+
+        let loaded = table.load();
+
+        // The first entry replaces the built-in version, the second differs by image size and the
+        // third is invalid
+        assert_eq!(loaded.len(), known.len() + 1);
+        let versions = loaded
+            .iter()
+            .filter(|details| details.version.version == "v1.01.748.0")
+            .map(|details| details.address_offsets.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0], vec![(AddressType::Version, vec![0x1000])].into_iter().collect());
+        assert_eq!(versions[1], vec![(AddressType::Version, vec![0x2000])].into_iter().collect());
+    }
+
+    #[test]
+    fn unreadable_version_tables_fall_back_to_the_built_in_versions() {
+        let table = TestVersionTable::new("unreadable_version_tables", "{not json");
+        assert_eq!(table.load().len(), known_process_details().len());
+
+        let table = TestVersionTable::new("version_table_of_the_wrong_shape", r#"{"name": "Tomb Raider"}"#);
+        assert_eq!(table.load().len(), known_process_details().len());
     }
 }
+
@@ -2,6 +2,7 @@ use reqwest::Url;
 use std::path::Path;
 use std::fs;
 use std::default::Default;
+use std::error::Error;
 use serde::de::DeserializeOwned;
 
 pub trait ReadableFromPath {
@@ -11,27 +12,38 @@ pub trait ReadableFromPath {
     ) -> Self
         where
             Self: std::marker::Sized + DeserializeOwned + Default
+    {
+        Self::try_from_path(path, typename).unwrap_or_else(|msg| panic!("{}", msg))
+    }
+
+    // Like from_path, but a file or url that can't be read or parsed is returned as an error
+    // rather than panicking
+    fn try_from_path(
+        path: &String,
+        typename: &String
+    ) -> Result<Self, Box<dyn Error>>
+        where
+            Self: std::marker::Sized + DeserializeOwned + Default
     {
         println!("Loading {} from {}", typename, path);
         let file_path = Path::new(path);
         let url = Url::parse(path);
 
         let content = if file_path.exists() {
-            fs::read_to_string(file_path).unwrap()
+            fs::read_to_string(file_path)?
         }
         else if url.is_ok() && !url.as_ref().unwrap().cannot_be_a_base() {
             reqwest::blocking::get(url.unwrap().as_str())
-                .expect(format!("Could not retrieve {} url", typename).as_str())
-                .text()
-                .unwrap()
+                .and_then(|response| response.text())
+                .map_err(|e| format!("Could not retrieve {} url: {}", typename, e))?
         }
         else {
             println!("Could not read {}, using default!", typename);
-            return Default::default();
+            return Ok(Default::default());
         };
 
         let result = serde_json::from_str(&content)
-            .expect(format!("Could not parse {} to expected format", typename).as_str());
-        return result;
+            .map_err(|e| format!("Could not parse {} to expected format: {}", typename, e))?;
+        Ok(result)
     }
 }
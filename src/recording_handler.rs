@@ -0,0 +1,356 @@
+use crate::action::Action;
+use crate::config::{RecordingConfig, RecordingFormat};
+use crate::error::HelperError;
+use crate::handler::Handler;
+use crate::memory_backend::MemoryBackend;
+use crate::process_details::{AddressOffsets, AddressType};
+use crate::tracked_memory::TrackedMemory;
+use crate::tracked_vector::TrackedVector;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const CSV_HEADER: &str = "time,x,y,z,look_at_x,look_at_y,look_at_z,camera_sin,camera_cos";
+
+// One sample of a trajectory, the time is in seconds since the recording started
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrajectorySample {
+    pub time: f64,
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub look_at: Option<[f32; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera: Option<[f32; 2]>,
+}
+
+impl TrajectorySample {
+    fn to_csv(&self) -> String {
+        let look_at = match self.look_at {
+            Some([x, y, z]) => format!("{},{},{}", x, y, z),
+            None => ",,".to_string(),
+        };
+        let camera = match self.camera {
+            Some([sin, cos]) => format!("{},{}", sin, cos),
+            None => ",".to_string(),
+        };
+        format!("{},{},{},{},{},{}", self.time, self.x, self.y, self.z, look_at, camera)
+    }
+}
+
+// Reads a recording written by this handler in either format, the format is taken from the
+// extension. Only the time and position columns are needed from CSV files.
+pub fn read_trajectory(path: &str) -> Result<Vec<TrajectorySample>, Box<dyn Error>> {
+    let content = fs::read_to_string(path)?;
+    let lines = content.lines().filter(|line| !line.trim().is_empty());
+
+    if path.ends_with(".jsonl") {
+        return lines
+            .map(|line| Ok(serde_json::from_str::<TrajectorySample>(line)?))
+            .collect();
+    }
+
+    lines
+        .skip(1)
+        .map(|line| {
+            let columns = line
+                .split(',')
+                .take(4)
+                .map(|column| column.trim().parse::<f64>())
+                .collect::<Result<Vec<_>, _>>()?;
+            match columns.as_slice() {
+                [time, x, y, z] => Ok(TrajectorySample {
+                    time: *time,
+                    x: *x as f32,
+                    y: *y as f32,
+                    z: *z as f32,
+                    look_at: None,
+                    camera: None,
+                }),
+                _ => Err(HelperError::new("Recording", format!("Too few columns in {:?}", line)).into()),
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+struct Recording {
+    path: PathBuf,
+    output: BufWriter<File>,
+    started: Instant,
+    last_sample: Option<Instant>,
+    sample_count: usize,
+}
+
+#[derive(Debug)]
+pub struct RecordingHandler {
+    position: TrackedVector,
+    look_at: Option<TrackedVector>,
+    camera: Option<[TrackedMemory<f32>; 2]>,
+    memory: Rc<dyn MemoryBackend>,
+    config: RecordingConfig,
+    recording: Option<Recording>,
+}
+
+impl RecordingHandler {
+    pub fn new(
+        address_offsets: &AddressOffsets,
+        base_addr: &usize,
+        memory: &Rc<dyn MemoryBackend>,
+        config: &RecordingConfig,
+    ) -> Option<RecordingHandler> {
+        println!("Loading recording handler...");
+
+        let tracked = |address_type: AddressType| -> Option<TrackedMemory<f32>> {
+            Some(TrackedMemory::new(
+                0.0,
+                address_offsets.get(&address_type)?.clone(),
+                *base_addr,
+            ))
+        };
+
+        Some(RecordingHandler {
+            position: TrackedVector::position(address_offsets, *base_addr)?,
+            look_at: TrackedVector::new(
+                address_offsets,
+                [AddressType::XLookAt, AddressType::YLookAt, AddressType::ZLookAt],
+                *base_addr,
+            ),
+            camera: match (tracked(AddressType::CameraSin), tracked(AddressType::CameraCos)) {
+                (Some(sin), Some(cos)) => Some([sin, cos]),
+                _ => None,
+            },
+            memory: memory.clone(),
+            config: config.clone(),
+            recording: None,
+        })
+    }
+
+    fn start(&mut self) -> Result<(), Box<dyn Error>> {
+        if self.recording.is_some() {
+            return Err(HelperError::new("Recording", "Already recording").into());
+        }
+
+        // Milliseconds so that stopping and starting again straight away doesn't reuse the name
+        let started_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+        let extension = match self.config.format {
+            RecordingFormat::Csv => "csv",
+            RecordingFormat::JsonLines => "jsonl",
+        };
+        let path = PathBuf::from(&self.config.directory)
+            .join(format!("tomb-helper-recording-{}.{}", started_at, extension));
+
+        let mut output = BufWriter::new(OpenOptions::new().write(true).create_new(true).open(&path)?);
+        if let RecordingFormat::Csv = self.config.format {
+            writeln!(output, "{}", CSV_HEADER)?;
+        }
+
+        println!("Started recording to {:?}", path);
+        self.recording = Some(Recording {
+            path,
+            output,
+            started: Instant::now(),
+            last_sample: None,
+            sample_count: 0,
+        });
+        Ok(())
+    }
+
+    fn stop(&mut self) -> Result<(), Box<dyn Error>> {
+        let mut recording = self
+            .recording
+            .take()
+            .ok_or_else(|| HelperError::new("Recording", "Not recording"))?;
+        recording.output.flush()?;
+        println!(
+            "Stopped recording, wrote {} samples to {:?}",
+            recording.sample_count, recording.path
+        );
+        Ok(())
+    }
+
+    fn record_sample(&mut self) -> Result<(), Box<dyn Error>> {
+        let interval = Duration::from_secs_f64(1.0 / self.config.sample_rate);
+        match &self.recording {
+            Some(recording) => {
+                if let Some(last_sample) = recording.last_sample {
+                    if last_sample.elapsed() < interval {
+                        return Ok(());
+                    }
+                }
+            }
+            None => return Ok(()),
+        }
+
+        // The position is unreadable during loading screens, those ticks are left out of the
+        // recording
+        let position = match self.position.fetch_all(&*self.memory) {
+            Some(position) => position,
+            None => return Ok(()),
+        };
+        let memory = &*self.memory;
+        let look_at = self.look_at.as_mut().and_then(|look_at| look_at.fetch_all(memory));
+        let camera = self.camera.as_mut().and_then(|[sin, cos]| {
+            sin.fetch_from_game(memory).ok()?;
+            cos.fetch_from_game(memory).ok()?;
+            Some([sin.data, cos.data])
+        });
+
+        let recording = self.recording.as_mut().unwrap();
+        let now = Instant::now();
+        let sample = TrajectorySample {
+            time: now.duration_since(recording.started).as_secs_f64(),
+            x: position[0],
+            y: position[1],
+            z: position[2],
+            look_at,
+            camera,
+        };
+        match self.config.format {
+            RecordingFormat::Csv => writeln!(recording.output, "{}", sample.to_csv())?,
+            RecordingFormat::JsonLines => writeln!(recording.output, "{}", serde_json::to_string(&sample)?)?,
+        }
+        recording.last_sample = Some(now);
+        recording.sample_count += 1;
+        Ok(())
+    }
+}
+
+impl Handler for RecordingHandler {
+    fn name(&self) -> &str {
+        "recording"
+    }
+
+    fn status(&self) -> String {
+        match &self.recording {
+            Some(recording) => format!(
+                "recording to {:?}, {} samples so far",
+                recording.path, recording.sample_count
+            ),
+            None => "not recording".to_string(),
+        }
+    }
+
+    // Nothing more can be recorded once the game has gone, so the recording is finished off
+    fn on_disconnect(&mut self) -> Result<(), Box<dyn Error>> {
+        match self.recording {
+            Some(_) => self.stop(),
+            None => Ok(()),
+        }
+    }
+
+    fn rebind(&mut self, base_addr: usize, memory: &Rc<dyn MemoryBackend>) {
+        self.position.rebind(base_addr);
+        if let Some(look_at) = &mut self.look_at {
+            look_at.rebind(base_addr);
+        }
+        if let Some(camera) = &mut self.camera {
+            for value in camera.iter_mut() {
+                value.rebind(base_addr);
+            }
+        }
+        self.memory = memory.clone();
+    }
+
+    fn handle_tick(&mut self) -> Result<(), Box<dyn Error>> {
+        self.record_sample()
+    }
+
+    fn handle_action(&mut self, action: Action) -> Result<(), Box<dyn Error>> {
+        match action {
+            Action::StartRecording {} => self.start(),
+            Action::StopRecording {} => self.stop(),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::process;
+
+    struct TestFile {
+        path: String,
+    }
+
+    impl TestFile {
+        fn new(name: &str, content: &str) -> TestFile {
+            let path = env::temp_dir().join(format!("tomb-helper-test-{}-{}", process::id(), name));
+            fs::write(&path, content).unwrap();
+            TestFile {
+                path: path.to_str().unwrap().to_string(),
+            }
+        }
+    }
+
+    impl Drop for TestFile {
+        fn drop(&mut self) {
+            fs::remove_file(&self.path).ok();
+        }
+    }
+
+    fn sample(time: f64, x: f32, y: f32, z: f32) -> TrajectorySample {
+        TrajectorySample {
+            time,
+            x,
+            y,
+            z,
+            look_at: None,
+            camera: None,
+        }
+    }
+
+    fn positions(samples: &[TrajectorySample]) -> Vec<(f64, f32, f32, f32)> {
+        samples.iter().map(|s| (s.time, s.x, s.y, s.z)).collect()
+    }
+
+    #[test]
+    fn reads_csv_recordings() {
+        let mut with_camera = sample(0.5, 4.0, 5.0, -6.0);
+        with_camera.look_at = Some([1.0, 1.0, 1.0]);
+        with_camera.camera = Some([0.0, 1.0]);
+        let content = format!(
+            "{}\n{}\n\n{}\n",
+            CSV_HEADER,
+            sample(0.0, 1.0, 2.0, 3.0).to_csv(),
+            with_camera.to_csv()
+        );
+        let file = TestFile::new("recording.csv", &content);
+
+        let samples = read_trajectory(&file.path).unwrap();
+
+        assert_eq!(positions(&samples), vec![(0.0, 1.0, 2.0, 3.0), (0.5, 4.0, 5.0, -6.0)]);
+    }
+
+    #[test]
+    fn reads_json_lines_recordings() {
+        let mut with_camera = sample(0.5, 4.0, 5.0, -6.0);
+        with_camera.camera = Some([0.0, 1.0]);
+        let content = [sample(0.0, 1.0, 2.0, 3.0), with_camera]
+            .iter()
+            .map(|sample| serde_json::to_string(sample).unwrap() + "\n")
+            .collect::<String>();
+        let file = TestFile::new("recording.jsonl", &content);
+
+        let samples = read_trajectory(&file.path).unwrap();
+
+        assert_eq!(positions(&samples), vec![(0.0, 1.0, 2.0, 3.0), (0.5, 4.0, 5.0, -6.0)]);
+        assert_eq!(samples[1].camera, Some([0.0, 1.0]));
+    }
+
+    #[test]
+    fn rejects_csv_rows_without_a_position() {
+        let file = TestFile::new("short.csv", &format!("{}\n0.0,1.0,2.0\n", CSV_HEADER));
+        assert!(read_trajectory(&file.path).is_err());
+
+        let file = TestFile::new("garbled.csv", &format!("{}\n0.0,1.0,two,3.0\n", CSV_HEADER));
+        assert!(read_trajectory(&file.path).is_err());
+    }
+}
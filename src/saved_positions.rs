@@ -0,0 +1,117 @@
+use crate::atomic_file::write_atomically;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedPosition {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    // Lara's rotation and velocity, for versions where they are known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation: Option<[f32; 3]>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub velocity: Option<[f32; 3]>,
+    #[serde(default)]
+    pub label: Option<String>,
+    // Seconds since the Unix epoch
+    #[serde(default)]
+    pub stored_at: u64,
+}
+
+impl SavedPosition {
+    pub fn new(x: f32, y: f32, z: f32, label: Option<String>) -> SavedPosition {
+        SavedPosition {
+            x,
+            y,
+            z,
+            rotation: None,
+            velocity: None,
+            label,
+            stored_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_secs()),
+        }
+    }
+}
+
+impl fmt::Display for SavedPosition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "({}, {}, {})", self.x, self.y, self.z)?;
+        if let Some([x, y, z]) = self.rotation {
+            write!(f, " facing ({}, {}, {})", x, y, z)?;
+        }
+        match &self.label {
+            Some(label) => write!(f, " {:?}", label),
+            None => Ok(()),
+        }
+    }
+}
+
+// Slot name to position
+pub type SavedSlots = HashMap<String, SavedPosition>;
+
+// Game name -> version -> handler name -> slots
+type SavedPositionsFile = HashMap<String, HashMap<String, HashMap<String, SavedSlots>>>;
+
+// The saved positions for one version of one game. Every handler reads and rewrites the whole
+// file when it stores so that they don't overwrite each other's slots.
+#[derive(Debug, Clone)]
+pub struct PositionStore {
+    path: PathBuf,
+    game: String,
+    version: String,
+}
+
+impl PositionStore {
+    pub fn new(path: &String, game: &String, version: &String) -> PositionStore {
+        PositionStore {
+            path: PathBuf::from(path),
+            game: game.clone(),
+            version: version.clone(),
+        }
+    }
+
+    fn read_file(&self) -> Result<SavedPositionsFile, Box<dyn Error>> {
+        if !self.path.exists() {
+            return Ok(SavedPositionsFile::new());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(&self.path)?)?)
+    }
+
+    pub fn load(&self, handler_name: &str) -> SavedSlots {
+        let slots = match self.read_file() {
+            Ok(mut file) => file
+                .remove(&self.game)
+                .and_then(|mut versions| versions.remove(&self.version))
+                .and_then(|mut handlers| handlers.remove(handler_name))
+                .unwrap_or_default(),
+            Err(msg) => {
+                eprintln!("Error: Could not load saved positions from {:?}: {}", self.path, msg);
+                SavedSlots::new()
+            }
+        };
+
+        if !slots.is_empty() {
+            println!("Loaded {} saved {} slots from {:?}", slots.len(), handler_name, self.path);
+        }
+        slots
+    }
+
+    pub fn save(&self, handler_name: &str, slots: &SavedSlots) -> Result<(), Box<dyn Error>> {
+        let mut file = self.read_file()?;
+        file.entry(self.game.clone())
+            .or_default()
+            .entry(self.version.clone())
+            .or_default()
+            .insert(handler_name.to_string(), slots.clone());
+        // Written atomically so that a crash part way through doesn't lose every saved position
+        write_atomically(&self.path, &serde_json::to_string_pretty(&file)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+use crate::config::SchedulerConfig;
+use crate::handler::Handler;
+use std::time::{Duration, Instant};
+
+// Keeps track of when each handler is next due a tick, so that the main loop can wait for actions
+// in between rather than spinning
+pub struct TickScheduler {
+    intervals: Vec<Duration>,
+    next_ticks: Vec<Instant>,
+}
+
+impl TickScheduler {
+    pub fn new(handlers: &[Box<dyn Handler>], config: &SchedulerConfig) -> TickScheduler {
+        TickScheduler::starting_at(handlers, config, Instant::now())
+    }
+
+    // Every handler is due its first tick at the start
+    fn starting_at(handlers: &[Box<dyn Handler>], config: &SchedulerConfig, start: Instant) -> TickScheduler {
+        let intervals = handlers
+            .iter()
+            .map(|handler| {
+                let rate = config
+                    .handler_tick_rates
+                    .get(handler.name())
+                    .copied()
+                    .or_else(|| handler.default_tick_rate())
+                    .unwrap_or(config.tick_rate);
+                Duration::from_secs_f64(1.0 / rate)
+            })
+            .collect::<Vec<_>>();
+
+        TickScheduler {
+            next_ticks: vec![start; intervals.len()],
+            intervals,
+        }
+    }
+
+    // The indices of the handlers due a tick, which are then scheduled for their next one
+    pub fn due(&mut self, now: Instant) -> Vec<usize> {
+        let mut due = vec![];
+        for (index, (next_tick, interval)) in self.next_ticks.iter_mut().zip(self.intervals.iter()).enumerate() {
+            if *next_tick > now {
+                continue;
+            }
+            due.push(index);
+            *next_tick += *interval;
+            // Ticks missed while e.g. the process was suspended are skipped rather than caught up
+            if *next_tick < now {
+                *next_tick = now + *interval;
+            }
+        }
+        due
+    }
+
+    pub fn next_tick(&self) -> Option<Instant> {
+        self.next_ticks.iter().min().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::Action;
+    use crate::memory_backend::MemoryBackend;
+    use std::error::Error;
+    use std::rc::Rc;
+
+    struct NamedHandler(&'static str, Option<f64>);
+
+    impl Handler for NamedHandler {
+        fn name(&self) -> &str {
+            self.0
+        }
+
+        fn default_tick_rate(&self) -> Option<f64> {
+            self.1
+        }
+
+        fn status(&self) -> String {
+            String::new()
+        }
+
+        fn handle_tick(&mut self) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+
+        fn handle_action(&mut self, _action: Action) -> Result<(), Box<dyn Error>> {
+            Ok(())
+        }
+
+        fn rebind(&mut self, _base_addr: usize, _memory: &Rc<dyn MemoryBackend>) {}
+    }
+
+    fn ms(milliseconds: u64) -> Duration {
+        Duration::from_millis(milliseconds)
+    }
+
+    // "fast" ticks every 250ms and "slow" every second
+    fn scheduler(start: Instant) -> TickScheduler {
+        let handlers: Vec<Box<dyn Handler>> =
+            vec![Box::new(NamedHandler("fast", None)), Box::new(NamedHandler("slow", None))];
+        let config = SchedulerConfig {
+            tick_rate: 1.0,
+            handler_tick_rates: vec![("fast".to_string(), 4.0)].into_iter().collect(),
+            ..SchedulerConfig::default()
+        };
+        TickScheduler::starting_at(&handlers, &config, start)
+    }
+
+    #[test]
+    fn ticks_handlers_at_their_own_rates() {
+        let start = Instant::now();
+        let mut scheduler = scheduler(start);
+
+        assert_eq!(scheduler.due(start), vec![0, 1]);
+        assert_eq!(scheduler.next_tick(), Some(start + ms(250)));
+        assert!(scheduler.due(start + ms(100)).is_empty());
+        assert_eq!(scheduler.due(start + ms(250)), vec![0]);
+        assert_eq!(scheduler.due(start + ms(500)), vec![0]);
+        assert_eq!(scheduler.due(start + ms(750)), vec![0]);
+        assert_eq!(scheduler.due(start + ms(1000)), vec![0, 1]);
+        assert_eq!(scheduler.next_tick(), Some(start + ms(1250)));
+    }
+
+    #[test]
+    fn late_ticks_keep_to_the_schedule() {
+        let start = Instant::now();
+        let mut scheduler = scheduler(start);
+        scheduler.due(start);
+
+        assert_eq!(scheduler.due(start + ms(300)), vec![0]);
+        assert_eq!(scheduler.next_tick(), Some(start + ms(500)));
+    }
+
+    #[test]
+    fn missed_ticks_are_skipped() {
+        let start = Instant::now();
+        let mut scheduler = scheduler(start);
+        scheduler.due(start);
+
+        // Only one tick each, however many were missed
+        assert_eq!(scheduler.due(start + ms(3100)), vec![0, 1]);
+        assert_eq!(scheduler.next_tick(), Some(start + ms(3350)));
+        assert!(scheduler.due(start + ms(3200)).is_empty());
+    }
+
+    #[test]
+    fn handler_default_rates_can_be_overridden() {
+        let start = Instant::now();
+        let handlers: Vec<Box<dyn Handler>> =
+            vec![Box::new(NamedHandler("telemetry", Some(2.0))), Box::new(NamedHandler("text", Some(2.0)))];
+        let config = SchedulerConfig {
+            tick_rate: 100.0,
+            handler_tick_rates: vec![("text".to_string(), 4.0)].into_iter().collect(),
+            ..SchedulerConfig::default()
+        };
+        let mut scheduler = TickScheduler::starting_at(&handlers, &config, start);
+
+        assert_eq!(scheduler.due(start), vec![0, 1]);
+        assert_eq!(scheduler.due(start + ms(250)), vec![1]);
+        assert_eq!(scheduler.due(start + ms(500)), vec![0, 1]);
+    }
+}
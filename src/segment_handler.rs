@@ -0,0 +1,493 @@
+use crate::action::{Action, PositionKind};
+use crate::atomic_file;
+use crate::config::SegmentConfig;
+use crate::error::HelperError;
+use crate::event::{Event, EventCategory};
+use crate::handler::Handler;
+use crate::memory_backend::MemoryBackend;
+use crate::process_details::AddressOffsets;
+use crate::tracked_vector::TrackedVector;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::mpsc::Sender;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Attempt {
+    // Seconds
+    time: f64,
+    // Seconds since the Unix epoch
+    completed_at: u64,
+}
+
+// Game name -> segment name -> attempts
+type SegmentHistoryFile = HashMap<String, HashMap<String, Vec<Attempt>>>;
+
+#[derive(Debug)]
+struct RunningSegment {
+    segment: SegmentConfig,
+    // None until the position handler has moved Lara to the start
+    started: Option<Instant>,
+    // Lara has to be seen outside the goal before reaching it counts, as she is still there when
+    // restarting straight after finishing
+    armed: bool,
+}
+
+// Times attempts at a segment from a stored start position to a goal zone
+#[derive(Debug)]
+pub struct SegmentHandler {
+    position: TrackedVector,
+    memory: Rc<dyn MemoryBackend>,
+    segments: Vec<SegmentConfig>,
+    history_path: PathBuf,
+    game: String,
+    running: Option<RunningSegment>,
+    last_segment: Option<String>,
+    // The slots the position handler has something stored in
+    position_slots: HashSet<String>,
+    actions: Sender<Action>,
+}
+
+impl SegmentHandler {
+    pub fn new(
+        address_offsets: &AddressOffsets,
+        base_addr: &usize,
+        memory: &Rc<dyn MemoryBackend>,
+        segments: &[SegmentConfig],
+        history_file: &String,
+        game: &String,
+        actions: &Sender<Action>,
+    ) -> Option<SegmentHandler> {
+        let segments = segments
+            .iter()
+            .filter(|segment| segment.game.as_ref().map_or(true, |segment_game| segment_game == game))
+            .cloned()
+            .collect::<Vec<_>>();
+        if segments.is_empty() {
+            return None;
+        }
+        println!("Loading segment practice handler with {} segments...", segments.len());
+
+        Some(SegmentHandler {
+            position: TrackedVector::position(address_offsets, *base_addr)?,
+            memory: memory.clone(),
+            segments,
+            history_path: PathBuf::from(history_file),
+            game: game.clone(),
+            running: None,
+            last_segment: None,
+            position_slots: HashSet::new(),
+            actions: actions.clone(),
+        })
+    }
+
+    // Without a name the last segment is restarted, or the first one if none have been run yet
+    fn start(&mut self, name: Option<String>) -> Result<(), Box<dyn Error>> {
+        let name = name.or_else(|| self.last_segment.clone());
+        let segment = match &name {
+            Some(name) => self.segments.iter().find(|segment| segment.name == *name),
+            None => self.segments.first(),
+        }
+        .ok_or_else(|| {
+            HelperError::new("Segment", format!("No segment named {:?}", name.unwrap_or_default()))
+        })?
+        .clone();
+        self.last_segment = Some(segment.name.clone());
+
+        if !self.position_slots.contains(&segment.start_slot) {
+            return Err(HelperError::new(
+                "Segment",
+                format!(
+                    "Segment {} starts from slot {}, which has no position stored",
+                    segment.name, segment.start_slot
+                ),
+            )
+            .into());
+        }
+        // Only the position handler restores the slot, and the timer starts once it has
+        self.actions.send(Action::RestorePositionSlot {
+            slot: segment.start_slot.clone(),
+            handler: Some(PositionKind::Position),
+        })?;
+        self.running = Some(RunningSegment {
+            segment,
+            started: None,
+            armed: false,
+        });
+        Ok(())
+    }
+
+    fn position_restored(&mut self, slot: &str) {
+        if let Some(running) = &mut self.running {
+            if running.started.is_none() && running.segment.start_slot == slot {
+                println!("Started segment {}", running.segment.name);
+                running.started = Some(Instant::now());
+            }
+        }
+    }
+
+    fn finish(&mut self, segment: &SegmentConfig, time: f64) -> Result<(), Box<dyn Error>> {
+        let mut history = self.read_history()?;
+        let attempts = history
+            .entry(self.game.clone())
+            .or_default()
+            .entry(segment.name.clone())
+            .or_default();
+        attempts.push(Attempt {
+            time,
+            completed_at: SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs(),
+        });
+
+        let (best, average) = summarise(attempts);
+        println!(
+            "Finished segment {} in {:.3}s! Best: {:.3}s, average: {:.3}s over {} attempts",
+            segment.name,
+            time,
+            best,
+            average,
+            attempts.len()
+        );
+
+        atomic_file::write_atomically(&self.history_path, &serde_json::to_string_pretty(&history)?)?;
+        Ok(())
+    }
+
+    fn read_history(&self) -> Result<SegmentHistoryFile, Box<dyn Error>> {
+        if !self.history_path.exists() {
+            return Ok(SegmentHistoryFile::new());
+        }
+        Ok(serde_json::from_str(&fs::read_to_string(&self.history_path)?)?)
+    }
+
+    fn update(&mut self) -> Result<(), Box<dyn Error>> {
+        let running = match &mut self.running {
+            Some(running) => running,
+            None => return Ok(()),
+        };
+        let elapsed = match running.started {
+            Some(started) => started.elapsed().as_secs_f64(),
+            None => return Ok(()),
+        };
+
+        if running.segment.timeout.map_or(false, |timeout| elapsed > timeout) {
+            println!("Segment {} timed out after {:.1}s, restarting", running.segment.name, elapsed);
+            let name = running.segment.name.clone();
+            // Stopped first so that if it can't be restarted it isn't retried every tick
+            self.running = None;
+            return self.start(Some(name));
+        }
+
+        // The position can't be read during loading screens, the goal is checked again afterwards
+        let position = match self.position.fetch_all(&*self.memory) {
+            Some(position) => position,
+            None => return Ok(()),
+        };
+
+        let in_goal = running.segment.goal.contains(position);
+        if !running.armed {
+            running.armed = !in_goal;
+            return Ok(());
+        }
+        if in_goal {
+            let segment = running.segment.clone();
+            self.running = None;
+            self.finish(&segment, elapsed)?;
+        }
+        Ok(())
+    }
+}
+
+// The best and average times of a segment's attempts
+fn summarise(attempts: &[Attempt]) -> (f64, f64) {
+    let best = attempts.iter().map(|attempt| attempt.time).fold(f64::INFINITY, f64::min);
+    let average = attempts.iter().map(|attempt| attempt.time).sum::<f64>() / attempts.len() as f64;
+    (best, average)
+}
+
+impl Handler for SegmentHandler {
+    fn name(&self) -> &str {
+        "segment practice"
+    }
+
+    fn status(&self) -> String {
+        match &self.running {
+            Some(RunningSegment {
+                segment,
+                started: Some(started),
+                ..
+            }) => format!("running segment {} for {:.2}s", segment.name, started.elapsed().as_secs_f64()),
+            Some(running) => format!("waiting to restore the start of segment {}", running.segment.name),
+            None => format!("{} segments, none running", self.segments.len()),
+        }
+    }
+
+    fn on_disconnect(&mut self) -> Result<(), Box<dyn Error>> {
+        self.handle_action(Action::StopSegment {})
+    }
+
+    fn rebind(&mut self, base_addr: usize, memory: &Rc<dyn MemoryBackend>) {
+        self.position.rebind(base_addr);
+        self.memory = memory.clone();
+    }
+
+    fn handle_tick(&mut self) -> Result<(), Box<dyn Error>> {
+        self.update()
+    }
+
+    fn handle_action(&mut self, action: Action) -> Result<(), Box<dyn Error>> {
+        match action {
+            Action::StartSegment { name } => self.start(name)?,
+            Action::StopSegment {} => {
+                if let Some(running) = self.running.take() {
+                    println!("Stopped segment {}", running.segment.name);
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+    fn subscriptions(&self) -> Vec<EventCategory> {
+        vec![EventCategory::Position]
+    }
+
+    fn handle_event(&mut self, event: &Event) -> Result<(), Box<dyn Error>> {
+        match event {
+            Event::SavedSlotsChanged {
+                handler: PositionKind::Position,
+                slots,
+            } => self.position_slots = slots.keys().cloned().collect(),
+            Event::PositionRestored {
+                handler: PositionKind::Position,
+                slot,
+                ..
+            } => self.position_restored(slot),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ZoneShape;
+    use crate::memory_backend::InMemoryBackend;
+    use crate::process_details::AddressType;
+    use crate::saved_positions::{SavedPosition, SavedSlots};
+    use process_memory::Architecture;
+    use std::sync::mpsc::{self, Receiver};
+    use std::time::Duration;
+    use std::{env, process, thread};
+
+    const X: usize = 0x100;
+    const Y: usize = 0x104;
+    const Z: usize = 0x108;
+
+    // A segment history file of its own for each test, removed when the test finishes
+    struct TestHistory {
+        path: String,
+    }
+
+    impl TestHistory {
+        fn new(name: &str) -> TestHistory {
+            let path = env::temp_dir().join(format!("tomb-helper-test-{}-{}.json", process::id(), name));
+            TestHistory {
+                path: path.to_str().unwrap().to_string(),
+            }
+        }
+    }
+
+    impl Drop for TestHistory {
+        fn drop(&mut self) {
+            fs::remove_file(&self.path).ok();
+        }
+    }
+
+    // The goal is a sphere around the origin
+    fn segment_handler(
+        history: &TestHistory,
+        timeout: f64,
+    ) -> (SegmentHandler, Rc<InMemoryBackend>, Receiver<Action>) {
+        let address_offsets: AddressOffsets = vec![
+            (AddressType::XPosition, vec![X]),
+            (AddressType::YPosition, vec![Y]),
+            (AddressType::ZPosition, vec![Z]),
+        ]
+        .into_iter()
+        .collect();
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let memory: Rc<dyn MemoryBackend> = backend.clone();
+        let segments = vec![SegmentConfig {
+            name: "Pit jump".to_string(),
+            game: None,
+            start_slot: "pit".to_string(),
+            goal: ZoneShape::Sphere {
+                center: [0.0, 0.0, 0.0],
+                radius: 1.0,
+            },
+            timeout: Some(timeout),
+        }];
+        let (tx, rx) = mpsc::channel();
+        let handler = SegmentHandler::new(
+            &address_offsets,
+            &0,
+            &memory,
+            &segments,
+            &history.path,
+            &"Tomb Raider".to_string(),
+            &tx,
+        )
+        .unwrap();
+        (handler, backend, rx)
+    }
+
+    fn move_lara(backend: &InMemoryBackend, [x, y, z]: [f32; 3]) {
+        backend.set(X, x);
+        backend.set(Y, y);
+        backend.set(Z, z);
+    }
+
+    fn slots_stored(handler: PositionKind, names: &[&str]) -> Event {
+        let slots = names
+            .iter()
+            .map(|name| (name.to_string(), SavedPosition::new(0.0, 0.0, 0.0, None)))
+            .collect::<SavedSlots>();
+        Event::SavedSlotsChanged { handler, slots }
+    }
+
+    fn restored(handler: PositionKind, slot: &str) -> Event {
+        Event::PositionRestored {
+            handler,
+            slot: slot.to_string(),
+            position: [0.0, 0.0, 0.0],
+        }
+    }
+
+    fn start() -> Action {
+        Action::StartSegment { name: None }
+    }
+
+    // Starts the segment and has the position handler restore its start
+    fn start_segment(handler: &mut SegmentHandler, actions: &Receiver<Action>) {
+        handler.handle_event(&slots_stored(PositionKind::Position, &["pit"])).unwrap();
+        handler.handle_action(start()).unwrap();
+        actions.try_recv().unwrap();
+        handler.handle_event(&restored(PositionKind::Position, "pit")).unwrap();
+    }
+
+    fn timer_started(handler: &SegmentHandler) -> bool {
+        matches!(handler.running, Some(RunningSegment { started: Some(_), .. }))
+    }
+
+    fn history_exists(history: &TestHistory) -> bool {
+        PathBuf::from(&history.path).exists()
+    }
+
+    fn attempt_times(handler: &SegmentHandler) -> Vec<f64> {
+        handler.read_history().unwrap()["Tomb Raider"]["Pit jump"]
+            .iter()
+            .map(|attempt| attempt.time)
+            .collect()
+    }
+
+    #[test]
+    fn restores_the_start_with_the_position_handler_only() {
+        let history = TestHistory::new("restores_the_start_with_the_position_handler_only");
+        let (mut handler, _backend, actions) = segment_handler(&history, 20.0);
+        handler.handle_event(&slots_stored(PositionKind::Position, &["pit"])).unwrap();
+
+        handler.handle_action(start()).unwrap();
+        match actions.try_recv() {
+            Ok(Action::RestorePositionSlot { slot, handler }) => {
+                assert_eq!(slot, "pit");
+                assert_eq!(handler, Some(PositionKind::Position));
+            }
+            other => panic!("expected the start slot to be restored, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn starts_the_timer_once_lara_is_at_the_start() {
+        let history = TestHistory::new("starts_the_timer_once_lara_is_at_the_start");
+        let (mut handler, _backend, _actions) = segment_handler(&history, 20.0);
+        handler.handle_event(&slots_stored(PositionKind::Position, &["pit"])).unwrap();
+        handler.handle_action(start()).unwrap();
+        assert!(!timer_started(&handler));
+
+        handler.handle_event(&restored(PositionKind::LookAt, "pit")).unwrap();
+        handler.handle_event(&restored(PositionKind::Position, "other")).unwrap();
+        assert!(!timer_started(&handler));
+
+        handler.handle_event(&restored(PositionKind::Position, "pit")).unwrap();
+        assert!(timer_started(&handler));
+    }
+
+    #[test]
+    fn does_not_start_without_a_stored_start_position() {
+        let history = TestHistory::new("does_not_start_without_a_stored_start_position");
+        let (mut handler, _backend, actions) = segment_handler(&history, 20.0);
+        handler.handle_event(&slots_stored(PositionKind::LookAt, &["pit"])).unwrap();
+
+        assert!(handler.handle_action(start()).is_err());
+        assert!(handler.running.is_none());
+        assert!(actions.try_recv().is_err());
+    }
+
+    #[test]
+    fn goal_only_counts_after_lara_has_left_it() {
+        let history = TestHistory::new("goal_only_counts_after_lara_has_left_it");
+        let (mut handler, backend, actions) = segment_handler(&history, 20.0);
+        move_lara(&backend, [0.0, 0.0, 0.0]);
+        start_segment(&mut handler, &actions);
+
+        handler.handle_tick().unwrap();
+        handler.handle_tick().unwrap();
+        assert!(handler.running.is_some());
+
+        move_lara(&backend, [5.0, 0.0, 0.0]);
+        handler.handle_tick().unwrap();
+        assert!(handler.running.is_some());
+
+        move_lara(&backend, [0.5, 0.0, 0.0]);
+        handler.handle_tick().unwrap();
+        assert!(handler.running.is_none());
+        assert_eq!(attempt_times(&handler).len(), 1);
+    }
+
+    #[test]
+    fn timing_out_restores_the_start_again() {
+        let history = TestHistory::new("timing_out_restores_the_start_again");
+        let (mut handler, backend, actions) = segment_handler(&history, 0.01);
+        move_lara(&backend, [5.0, 0.0, 0.0]);
+        start_segment(&mut handler, &actions);
+
+        thread::sleep(Duration::from_millis(20));
+        handler.handle_tick().unwrap();
+        match actions.try_recv() {
+            Ok(Action::RestorePositionSlot { slot, .. }) => assert_eq!(slot, "pit"),
+            other => panic!("expected the start slot to be restored, got {:?}", other),
+        }
+        assert!(handler.running.is_some());
+        assert!(!timer_started(&handler));
+        assert!(!history_exists(&history));
+    }
+
+    #[test]
+    fn history_keeps_every_attempt() {
+        let history = TestHistory::new("history_keeps_every_attempt");
+        let (mut handler, _backend, _actions) = segment_handler(&history, 20.0);
+        let segment = handler.segments[0].clone();
+
+        handler.finish(&segment, 2.0).unwrap();
+        handler.finish(&segment, 4.0).unwrap();
+        handler.finish(&segment, 3.0).unwrap();
+
+        let attempts = &handler.read_history().unwrap()["Tomb Raider"]["Pit jump"];
+        assert_eq!(attempt_times(&handler), vec![2.0, 4.0, 3.0]);
+        assert_eq!(summarise(attempts), (2.0, 3.0));
+    }
+}
@@ -0,0 +1,146 @@
+use crate::find_process::get_image_size;
+use crate::memory_backend::MemoryBackend;
+use crate::process_details::{ProcessDetails, Signature, SignatureRule};
+use std::convert::TryInto;
+use std::io;
+
+// Unreadable chunks of the image are left zeroed, so keep them small enough that one bad page
+// doesn't hide much of the image
+const CHUNK_SIZE: usize = 0x1_0000;
+
+// Fills in any address types that have a signature but no fixed offsets by scanning the module
+// image for each signature
+pub fn resolve_signatures(memory: &dyn MemoryBackend, base_addr: usize, details: &mut ProcessDetails) {
+    let missing = details
+        .signatures
+        .iter()
+        .filter(|(address_type, _)| !details.address_offsets.contains_key(address_type))
+        .collect::<Vec<_>>();
+    if missing.is_empty() {
+        return;
+    }
+
+    println!("Scanning for {} signatures...", missing.len());
+    let image = match read_image(memory, base_addr) {
+        Ok(image) => image,
+        Err(msg) => {
+            eprintln!("Error: Could not read module image for signature scanning: {}", msg);
+            return;
+        }
+    };
+
+    for (address_type, signature) in missing {
+        match find_signature(&image, base_addr, signature) {
+            Some(offsets) => {
+                println!("Found {:?} by signature at {:#x?}", address_type, offsets);
+                details.address_offsets.insert(*address_type, offsets);
+            }
+            None => eprintln!("Error: Could not find signature for {:?}", address_type),
+        }
+    }
+}
+
+fn read_image(memory: &dyn MemoryBackend, base_addr: usize) -> io::Result<Vec<u8>> {
+    let mut image = vec![0_u8; get_image_size(memory, base_addr)?];
+    for (index, chunk) in image.chunks_mut(CHUNK_SIZE).enumerate() {
+        // Some pages of the image (e.g. guard pages) can't be read, just skip them
+        let _ = memory.read_bytes(base_addr + index * CHUNK_SIZE, chunk);
+    }
+    Ok(image)
+}
+
+// Returns the pointer chain for the first match of the signature, with the first offset relative
+// to the image base like the fixed offsets in process_details
+fn find_signature(image: &[u8], base_addr: usize, signature: &Signature) -> Option<Vec<usize>> {
+    let pattern = signature.parse_pattern().ok()?;
+    let found = image.windows(pattern.len()).position(|window| {
+        window
+            .iter()
+            .zip(pattern.iter())
+            .all(|(byte, expected)| expected.map_or(true, |expected| *byte == expected))
+    })?;
+
+    let operand = |operand_offset: usize| -> Option<[u8; 4]> {
+        image.get(found + operand_offset..found + operand_offset + 4)?.try_into().ok()
+    };
+
+    let image_offset = match signature.rule {
+        SignatureRule::Match { offset } => (found as isize + offset) as usize,
+        SignatureRule::Absolute { operand_offset } => {
+            // The loader has already relocated the operand to the actual base address
+            let address = u32::from_le_bytes(operand(operand_offset)?) as usize;
+            address.wrapping_sub(base_addr)
+        }
+        SignatureRule::RipRelative {
+            operand_offset,
+            instruction_length,
+        } => {
+            let displacement = i32::from_le_bytes(operand(operand_offset)?) as isize;
+            (found as isize + instruction_length as isize + displacement) as usize
+        }
+    };
+
+    let mut offsets = vec![image_offset];
+    offsets.extend(signature.offsets.iter());
+    Some(offsets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE_ADDR: usize = 0x40_0000;
+
+    fn signature(pattern: &str, rule: SignatureRule) -> Signature {
+        Signature {
+            pattern: pattern.to_string(),
+            rule,
+            offsets: vec![0x10, 0x4],
+        }
+    }
+
+    fn image() -> Vec<u8> {
+        let mut image = vec![0_u8; 0x100];
+        // mov rax, [rip + 0x1234]; test rax, rax
+        image[0x20..0x2a].copy_from_slice(&[0x48, 0x8B, 0x05, 0x34, 0x12, 0x00, 0x00, 0x48, 0x85, 0xC0]);
+        // mov eax, [0x00405678]; test eax, eax
+        image[0x40..0x47].copy_from_slice(&[0xA1, 0x78, 0x56, 0x40, 0x00, 0x85, 0xC0]);
+        image
+    }
+
+    #[test]
+    fn matches_wildcards() {
+        let found = find_signature(
+            &image(),
+            BASE_ADDR,
+            &signature("48 8B 05 ?? ?? ?? ?? 48 85 C0", SignatureRule::Match { offset: 3 }),
+        );
+        assert_eq!(found, Some(vec![0x23, 0x10, 0x4]));
+
+        let found = find_signature(&image(), BASE_ADDR, &signature("A1 ? ? ? ? 85 C0", SignatureRule::default()));
+        assert_eq!(found, Some(vec![0x40, 0x10, 0x4]));
+    }
+
+    #[test]
+    fn follows_instruction_operands() {
+        let rip_relative = SignatureRule::RipRelative {
+            operand_offset: 3,
+            instruction_length: 7,
+        };
+        let found = find_signature(&image(), BASE_ADDR, &signature("48 8B 05 ?? ?? ?? ?? 48 85 C0", rip_relative));
+        assert_eq!(found, Some(vec![0x20 + 7 + 0x1234, 0x10, 0x4]));
+
+        let absolute = SignatureRule::Absolute { operand_offset: 1 };
+        let found = find_signature(&image(), BASE_ADDR, &signature("A1 ?? ?? ?? ?? 85 C0", absolute));
+        assert_eq!(found, Some(vec![0x5678, 0x10, 0x4]));
+    }
+
+    #[test]
+    fn finds_nothing_for_missing_or_invalid_patterns() {
+        let found = find_signature(&image(), BASE_ADDR, &signature("48 8B 05 ?? ?? ?? ?? 48 85 C1", SignatureRule::default()));
+        assert_eq!(found, None);
+
+        let found = find_signature(&image(), BASE_ADDR, &signature("48 8B XX", SignatureRule::default()));
+        assert_eq!(found, None);
+    }
+}
@@ -0,0 +1,260 @@
+use crate::action::{Action, PositionKind};
+use crate::atomic_file::write_atomically;
+use crate::config::{TelemetryConfig, TelemetryOutput};
+use crate::event::{Event, EventCategory};
+use crate::handler::Handler;
+use crate::memory_backend::MemoryBackend;
+use crate::process_details::AddressOffsets;
+use crate::tracked_vector::TrackedVector;
+use serde_json::json;
+use std::error::Error;
+use std::rc::Rc;
+use std::time::Instant;
+
+// Updates per second unless handler_tick_rates sets the telemetry rate
+const DEFAULT_RATE: f64 = 10.0;
+
+// Regularly reports Lara's position and speed, e.g. for speedometer overlays
+#[derive(Debug)]
+pub struct TelemetryHandler {
+    position: TrackedVector,
+    memory: Rc<dyn MemoryBackend>,
+    config: TelemetryConfig,
+    last_sample: Option<(Instant, [f32; 3])>,
+    saved_position: Option<[f32; 3]>,
+    events: Vec<Event>,
+}
+
+impl TelemetryHandler {
+    pub fn new(
+        address_offsets: &AddressOffsets,
+        base_addr: &usize,
+        memory: &Rc<dyn MemoryBackend>,
+        config: &TelemetryConfig,
+    ) -> Option<TelemetryHandler> {
+        if let TelemetryOutput::Off {} = config.output {
+            return None;
+        }
+        println!("Loading telemetry handler...");
+
+        Some(TelemetryHandler {
+            position: TrackedVector::position(address_offsets, *base_addr)?,
+            memory: memory.clone(),
+            config: config.clone(),
+            last_sample: None,
+            saved_position: None,
+            events: vec![],
+        })
+    }
+
+    fn update(&mut self, now: Instant) -> Result<(), Box<dyn Error>> {
+        // Speeds are left at zero for the first sample after the position becomes readable again,
+        // rather than measuring across a loading screen
+        let position = match self.position.fetch_all(&*self.memory) {
+            Some(position) => position,
+            None => {
+                self.last_sample = None;
+                return Ok(());
+            }
+        };
+
+        let (horizontal_speed, vertical_speed) = match self.last_sample {
+            Some((last_time, last_position)) => {
+                let elapsed = now.duration_since(last_time).as_secs_f32();
+                let horizontal = ((position[0] - last_position[0]).powi(2)
+                    + (position[1] - last_position[1]).powi(2))
+                .sqrt();
+                (horizontal / elapsed, (position[2] - last_position[2]) / elapsed)
+            }
+            None => (0.0, 0.0),
+        };
+        self.last_sample = Some((now, position));
+
+        let distance_from_saved = self.saved_position.map(|saved| {
+            (0..3)
+                .map(|i| (position[i] - saved[i]).powi(2))
+                .sum::<f32>()
+                .sqrt()
+        });
+
+        let event = Event::Telemetry {
+            position,
+            horizontal_speed,
+            vertical_speed,
+            distance_from_saved,
+        };
+        match &self.config.output {
+            TelemetryOutput::Off {} => {}
+            TelemetryOutput::Print {} => println!("{}", event),
+            TelemetryOutput::File { path } => {
+                let content = json!({
+                    "x": position[0],
+                    "y": position[1],
+                    "z": position[2],
+                    "horizontal_speed": horizontal_speed,
+                    "vertical_speed": vertical_speed,
+                    "distance_from_saved": distance_from_saved,
+                });
+                write_atomically(path, &content.to_string())?;
+            }
+        }
+        self.events.push(event);
+        Ok(())
+    }
+}
+
+impl Handler for TelemetryHandler {
+    fn name(&self) -> &str {
+        "telemetry"
+    }
+
+    fn status(&self) -> String {
+        match &self.config.output {
+            TelemetryOutput::Off {} => "off".to_string(),
+            TelemetryOutput::Print {} => "printing".to_string(),
+            TelemetryOutput::File { path } => format!("writing to {}", path),
+        }
+    }
+
+    fn rebind(&mut self, base_addr: usize, memory: &Rc<dyn MemoryBackend>) {
+        self.position.rebind(base_addr);
+        self.last_sample = None;
+        self.memory = memory.clone();
+    }
+
+    fn handle_tick(&mut self) -> Result<(), Box<dyn Error>> {
+        self.update(Instant::now())
+    }
+
+    fn default_tick_rate(&self) -> Option<f64> {
+        Some(DEFAULT_RATE)
+    }
+
+    fn handle_action(&mut self, _action: Action) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn take_events(&mut self) -> Vec<Event> {
+        self.events.drain(..).collect()
+    }
+
+    fn subscriptions(&self) -> Vec<EventCategory> {
+        vec![EventCategory::Position]
+    }
+
+    fn handle_event(&mut self, event: &Event) -> Result<(), Box<dyn Error>> {
+        if let Event::SelectedPositionChanged { handler, position } = event {
+            if *handler == PositionKind::Position {
+                self.saved_position = *position;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_backend::InMemoryBackend;
+    use crate::process_details::AddressType;
+    use process_memory::Architecture;
+    use std::time::Duration;
+
+    const X: usize = 0x100;
+    const Y: usize = 0x104;
+    const Z: usize = 0x108;
+
+    fn telemetry_handler(backend: &Rc<InMemoryBackend>) -> TelemetryHandler {
+        let address_offsets: AddressOffsets = vec![
+            (AddressType::XPosition, vec![X]),
+            (AddressType::YPosition, vec![Y]),
+            (AddressType::ZPosition, vec![Z]),
+        ]
+        .into_iter()
+        .collect();
+        let memory: Rc<dyn MemoryBackend> = backend.clone();
+        let config = TelemetryConfig {
+            output: TelemetryOutput::Print {},
+        };
+        TelemetryHandler::new(&address_offsets, &0, &memory, &config).unwrap()
+    }
+
+    fn move_lara(backend: &InMemoryBackend, [x, y, z]: [f32; 3]) {
+        backend.set(X, x);
+        backend.set(Y, y);
+        backend.set(Z, z);
+    }
+
+    // The speeds and distance of the only telemetry reported since the last call
+    fn reported(handler: &mut TelemetryHandler) -> (f32, f32, Option<f32>) {
+        match handler.take_events().as_slice() {
+            [Event::Telemetry {
+                horizontal_speed,
+                vertical_speed,
+                distance_from_saved,
+                ..
+            }] => (*horizontal_speed, *vertical_speed, *distance_from_saved),
+            other => panic!("expected one telemetry event, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn measures_speed_between_samples() {
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let mut handler = telemetry_handler(&backend);
+        let start = Instant::now();
+
+        move_lara(&backend, [0.0, 0.0, 10.0]);
+        handler.update(start).unwrap();
+        assert_eq!(reported(&mut handler), (0.0, 0.0, None));
+
+        move_lara(&backend, [3.0, 4.0, 8.0]);
+        handler.update(start + Duration::from_millis(500)).unwrap();
+        assert_eq!(reported(&mut handler), (10.0, -4.0, None));
+    }
+
+    #[test]
+    fn measures_distance_from_the_selected_position() {
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let mut handler = telemetry_handler(&backend);
+        move_lara(&backend, [3.0, 4.0, 0.0]);
+
+        handler
+            .handle_event(&Event::SelectedPositionChanged {
+                handler: PositionKind::Position,
+                position: Some([0.0, 0.0, 12.0]),
+            })
+            .unwrap();
+        // The look at position isn't where Lara is headed
+        handler
+            .handle_event(&Event::SelectedPositionChanged {
+                handler: PositionKind::LookAt,
+                position: Some([100.0, 0.0, 0.0]),
+            })
+            .unwrap();
+        handler.update(Instant::now()).unwrap();
+        assert_eq!(reported(&mut handler).2, Some(13.0));
+
+        handler
+            .handle_event(&Event::SelectedPositionChanged {
+                handler: PositionKind::Position,
+                position: None,
+            })
+            .unwrap();
+        handler.update(Instant::now()).unwrap();
+        assert_eq!(reported(&mut handler).2, None);
+    }
+
+    #[test]
+    fn reports_on_every_tick() {
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let mut handler = telemetry_handler(&backend);
+        move_lara(&backend, [0.0, 0.0, 0.0]);
+
+        // How often is left to the scheduler, at 10 a second unless configured otherwise
+        assert_eq!(handler.default_tick_rate(), Some(10.0));
+        handler.handle_tick().unwrap();
+        handler.handle_tick().unwrap();
+        assert_eq!(handler.take_events().len(), 2);
+    }
+}
@@ -0,0 +1,212 @@
+use crate::action::Action;
+use crate::atomic_file::write_atomically;
+use crate::config::TextOutputConfig;
+use crate::event::{Event, EventCategory};
+use crate::handler::Handler;
+use crate::memory_backend::MemoryBackend;
+use crate::process_details::AddressOffsets;
+use crate::tracked_vector::TrackedVector;
+use std::collections::HashMap;
+use std::error::Error;
+use std::rc::Rc;
+
+const POSITION_PLACEHOLDERS: [&str; 3] = ["{x}", "{y}", "{z}"];
+
+#[derive(Debug)]
+struct TextOutput {
+    config: TextOutputConfig,
+    // What was last written, so that files are only rewritten when their text changes
+    written: Option<String>,
+}
+
+// Keeps small text files up to date for e.g. OBS text sources to show
+#[derive(Debug)]
+pub struct TextOutputHandler {
+    outputs: Vec<TextOutput>,
+    // Only read if an output uses the position
+    position: Option<TrackedVector>,
+    memory: Rc<dyn MemoryBackend>,
+    values: HashMap<&'static str, String>,
+}
+
+impl TextOutputHandler {
+    pub fn new(
+        address_offsets: &AddressOffsets,
+        base_addr: &usize,
+        memory: &Rc<dyn MemoryBackend>,
+        outputs: &[TextOutputConfig],
+    ) -> Option<TextOutputHandler> {
+        if outputs.is_empty() {
+            return None;
+        }
+        println!("Loading text output handler with {} files...", outputs.len());
+
+        let uses_position = outputs.iter().any(|output| {
+            POSITION_PLACEHOLDERS
+                .iter()
+                .any(|placeholder| output.template.contains(placeholder))
+        });
+        let position = if uses_position {
+            TrackedVector::position(address_offsets, *base_addr)
+        } else {
+            None
+        };
+
+        Some(TextOutputHandler {
+            outputs: outputs
+                .iter()
+                .map(|config| TextOutput {
+                    config: config.clone(),
+                    written: None,
+                })
+                .collect(),
+            position,
+            memory: memory.clone(),
+            values: HashMap::new(),
+        })
+    }
+
+    // Placeholders without a value yet are left empty
+    fn render(&self, template: &str) -> String {
+        ["{time_saved_rta}", "{time_saved_igt}", "{cutscene_id}", "{x}", "{y}", "{z}"]
+            .iter()
+            .fold(template.to_string(), |text, placeholder| {
+                let value = self.values.get(placeholder).map_or("", |value| value.as_str());
+                text.replace(placeholder, value)
+            })
+    }
+
+    fn write_outputs(&mut self) -> Result<(), Box<dyn Error>> {
+        for i in 0..self.outputs.len() {
+            let text = self.render(&self.outputs[i].config.template);
+            let output = &mut self.outputs[i];
+            if output.written.as_ref() != Some(&text) {
+                write_atomically(&output.config.path, &text)?;
+                output.written = Some(text);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Handler for TextOutputHandler {
+    fn name(&self) -> &str {
+        "text outputs"
+    }
+
+    fn status(&self) -> String {
+        format!("writing {} files", self.outputs.len())
+    }
+
+    fn rebind(&mut self, base_addr: usize, memory: &Rc<dyn MemoryBackend>) {
+        if let Some(position) = &mut self.position {
+            position.rebind(base_addr);
+        }
+        self.memory = memory.clone();
+    }
+
+    fn handle_tick(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(position) = &mut self.position {
+            // The last known position is kept through loading screens
+            if let Some(position) = position.fetch_all(&*self.memory) {
+                for (placeholder, coordinate) in POSITION_PLACEHOLDERS.iter().zip(position.iter()) {
+                    self.values.insert(placeholder, format!("{:.0}", coordinate));
+                }
+            }
+        }
+        self.write_outputs()
+    }
+
+    fn handle_action(&mut self, _action: Action) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    fn subscriptions(&self) -> Vec<EventCategory> {
+        vec![EventCategory::Cutscene]
+    }
+
+    fn handle_event(&mut self, event: &Event) -> Result<(), Box<dyn Error>> {
+        match event {
+            Event::CutsceneTimeSaved { rta, igt } => {
+                self.values.insert("{time_saved_rta}", format!("{:.2}", rta));
+                self.values.insert("{time_saved_igt}", format!("{:.2}", igt));
+            }
+            Event::CutsceneChanged { id } => {
+                self.values
+                    .insert("{cutscene_id}", id.map_or(String::new(), |id| id.to_string()));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory_backend::InMemoryBackend;
+    use crate::process_details::AddressType;
+    use process_memory::Architecture;
+    use std::{env, fs, process};
+
+    const X: usize = 0x100;
+    const Y: usize = 0x104;
+    const Z: usize = 0x108;
+
+    fn text_output_handler(backend: &Rc<InMemoryBackend>, path: &str, template: &str) -> TextOutputHandler {
+        let address_offsets: AddressOffsets = vec![
+            (AddressType::XPosition, vec![X]),
+            (AddressType::YPosition, vec![Y]),
+            (AddressType::ZPosition, vec![Z]),
+        ]
+        .into_iter()
+        .collect();
+        let memory: Rc<dyn MemoryBackend> = backend.clone();
+        let outputs = vec![TextOutputConfig {
+            path: path.to_string(),
+            template: template.to_string(),
+        }];
+        TextOutputHandler::new(&address_offsets, &0, &memory, &outputs).unwrap()
+    }
+
+    #[test]
+    fn renders_cutscene_values() {
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let mut handler = text_output_handler(&backend, "unused.txt", "");
+
+        handler.handle_event(&Event::CutsceneTimeSaved { rta: 12.345, igt: 6.0 }).unwrap();
+        handler.handle_event(&Event::CutsceneChanged { id: Some(42) }).unwrap();
+        assert_eq!(
+            handler.render("Saved {time_saved_rta}s ({time_saved_igt}s), in {cutscene_id}"),
+            "Saved 12.35s (6.00s), in 42"
+        );
+
+        handler.handle_event(&Event::CutsceneChanged { id: None }).unwrap();
+        assert_eq!(handler.render("[{cutscene_id}]"), "[]");
+    }
+
+    #[test]
+    fn leaves_missing_values_empty_and_other_text_alone() {
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let handler = text_output_handler(&backend, "unused.txt", "");
+
+        assert_eq!(handler.render("{x},{y},{z} {time_saved_rta}"), ",, ");
+        assert_eq!(handler.render("{unknown} {{x}}"), "{unknown} {}");
+    }
+
+    #[test]
+    fn renders_and_writes_the_position() {
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let path = env::temp_dir().join(format!("tomb-helper-test-{}-position.txt", process::id()));
+        let path = path.to_str().unwrap();
+        let mut handler = text_output_handler(&backend, path, "({x}, {y}, {z})");
+        backend.set(X, 1.4_f32);
+        backend.set(Y, -2.6_f32);
+        backend.set(Z, 100.0_f32);
+
+        handler.handle_tick().unwrap();
+        let written = fs::read_to_string(path);
+        fs::remove_file(path).ok();
+        assert_eq!(written.unwrap(), "(1, -3, 100)");
+    }
+}
@@ -1,11 +1,10 @@
-use process_memory::{Architecture, DataMember, Memory, ProcessHandle};
+use crate::memory_backend::{read_value, write_value, MemoryBackend};
 use std::io;
 
 #[derive(Debug, Clone)]
 pub struct TrackedMemory<T: Copy> {
     pub data: T,
     offsets: Vec<usize>,
-    arch: Architecture,
     base_addr: usize,
 }
 
@@ -13,34 +12,29 @@ impl<T: Copy + std::fmt::Debug> TrackedMemory<T> {
     pub fn new(
         data: T,
         offsets: Vec<usize>,
-        arch: Architecture,
         base_addr: usize,
     ) -> TrackedMemory<T> {
         TrackedMemory {
             data,
             offsets,
-            arch,
             base_addr,
         }
     }
 
-    pub fn offsets_with_base(&self) -> Vec<usize> {
-        let mut offsets_with_base = self.offsets.clone();
-        offsets_with_base[0] += self.base_addr;
-        offsets_with_base
+    // For when the game has been restarted and loaded at a different address
+    pub fn rebind(&mut self, base_addr: usize) {
+        self.base_addr = base_addr;
     }
 
-    pub fn fetch_from_game(&mut self, handle: ProcessHandle) -> io::Result<()> {
-        self.data = DataMember::<T>::new_offset(handle, self.offsets_with_base())
-            .set_arch(self.arch)
-            .read()?;
+    pub fn fetch_from_game(&mut self, memory: &dyn MemoryBackend) -> io::Result<()> {
+        let addr = memory.resolve_from_base(&self.offsets, self.base_addr)?;
+        self.data = read_value(memory, addr)?;
         Ok(())
     }
 
-    pub fn apply_to_game(&self, handle: ProcessHandle) -> io::Result<()> {
-        DataMember::<T>::new_offset(handle, self.offsets_with_base())
-            .set_arch(self.arch)
-            .write(&self.data)?;
+    pub fn apply_to_game(&self, memory: &dyn MemoryBackend) -> io::Result<()> {
+        let addr = memory.resolve_from_base(&self.offsets, self.base_addr)?;
+        write_value(memory, addr, &self.data)?;
         Ok(())
     }
 }
@@ -0,0 +1,63 @@
+use crate::memory_backend::MemoryBackend;
+use crate::process_details::{AddressOffsets, AddressType};
+use crate::tracked_memory::TrackedMemory;
+use std::io;
+
+// Three values which are read and written together, e.g. Lara's X, Y and Z position
+#[derive(Debug, Clone)]
+pub struct TrackedVector {
+    components: [TrackedMemory<f32>; 3],
+}
+
+impl TrackedVector {
+    // None if the game version doesn't have an address for any of the components
+    pub fn new(
+        address_offsets: &AddressOffsets,
+        address_types: [AddressType; 3],
+        base_addr: usize,
+    ) -> Option<TrackedVector> {
+        let [x, y, z] = address_types;
+        Some(TrackedVector {
+            components: [
+                TrackedMemory::new(0.0, address_offsets.get(&x)?.clone(), base_addr),
+                TrackedMemory::new(0.0, address_offsets.get(&y)?.clone(), base_addr),
+                TrackedMemory::new(0.0, address_offsets.get(&z)?.clone(), base_addr),
+            ],
+        })
+    }
+
+    pub fn position(address_offsets: &AddressOffsets, base_addr: usize) -> Option<TrackedVector> {
+        TrackedVector::new(
+            address_offsets,
+            [AddressType::XPosition, AddressType::YPosition, AddressType::ZPosition],
+            base_addr,
+        )
+    }
+
+    pub fn rebind(&mut self, base_addr: usize) {
+        for component in self.components.iter_mut() {
+            component.rebind(base_addr);
+        }
+    }
+
+    // None if any component can't be read, e.g. Lara's position during loading screens
+    pub fn fetch_all(&mut self, memory: &dyn MemoryBackend) -> Option<[f32; 3]> {
+        self.fetch_from_game(memory).ok()
+    }
+
+    pub fn fetch_from_game(&mut self, memory: &dyn MemoryBackend) -> io::Result<[f32; 3]> {
+        for component in self.components.iter_mut() {
+            component.fetch_from_game(memory)?;
+        }
+        let [x, y, z] = &self.components;
+        Ok([x.data, y.data, z.data])
+    }
+
+    pub fn apply_to_game(&mut self, values: [f32; 3], memory: &dyn MemoryBackend) -> io::Result<()> {
+        for (component, value) in self.components.iter_mut().zip(values.iter()) {
+            component.data = *value;
+            component.apply_to_game(memory)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+use crate::action::Action;
+use crate::config::TriggerZone;
+use crate::handler::Handler;
+use crate::memory_backend::MemoryBackend;
+use crate::process_details::AddressOffsets;
+use crate::tracked_vector::TrackedVector;
+use std::error::Error;
+use std::rc::Rc;
+use std::sync::mpsc::Sender;
+
+// Sends the actions of zones as Lara enters and leaves them, down the same channel as hotkeys
+#[derive(Debug)]
+pub struct TriggerZoneHandler {
+    position: TrackedVector,
+    memory: Rc<dyn MemoryBackend>,
+    zones: Vec<TriggerZone>,
+    // Which zones Lara was in at the last readable position, None until the first one so that
+    // connecting while in a zone doesn't trigger it
+    inside: Option<Vec<bool>>,
+    actions: Sender<Action>,
+}
+
+impl TriggerZoneHandler {
+    pub fn new(
+        address_offsets: &AddressOffsets,
+        base_addr: &usize,
+        memory: &Rc<dyn MemoryBackend>,
+        zones: &[TriggerZone],
+        game: &str,
+        actions: &Sender<Action>,
+    ) -> Option<TriggerZoneHandler> {
+        let zones = zones
+            .iter()
+            .filter(|zone| zone.game.as_ref().map_or(true, |zone_game| zone_game == game))
+            .cloned()
+            .collect::<Vec<_>>();
+        if zones.is_empty() {
+            return None;
+        }
+        println!("Loading trigger zone handler with {} zones...", zones.len());
+
+        Some(TriggerZoneHandler {
+            position: TrackedVector::position(address_offsets, *base_addr)?,
+            memory: memory.clone(),
+            zones,
+            inside: None,
+            actions: actions.clone(),
+        })
+    }
+}
+
+impl Handler for TriggerZoneHandler {
+    fn name(&self) -> &str {
+        "trigger zones"
+    }
+
+    fn status(&self) -> String {
+        let inside = match &self.inside {
+            Some(inside) => self
+                .zones
+                .iter()
+                .zip(inside.iter())
+                .filter(|(_, inside)| **inside)
+                .map(|(zone, _)| zone.name.as_str())
+                .collect::<Vec<_>>(),
+            None => vec![],
+        };
+        format!("{} zones, inside {:?}", self.zones.len(), inside)
+    }
+
+    fn rebind(&mut self, base_addr: usize, memory: &Rc<dyn MemoryBackend>) {
+        self.position.rebind(base_addr);
+        // Lara may be somewhere else entirely after the restart
+        self.inside = None;
+        self.memory = memory.clone();
+    }
+
+    fn handle_tick(&mut self) -> Result<(), Box<dyn Error>> {
+        // The position can't be read during loading screens, zones are checked again afterwards
+        let position = match self.position.fetch_all(&*self.memory) {
+            Some(position) => position,
+            None => return Ok(()),
+        };
+
+        let now_inside = self
+            .zones
+            .iter()
+            .map(|zone| zone.shape.contains(position))
+            .collect::<Vec<_>>();
+        if let Some(was_inside) = &self.inside {
+            for ((zone, was_inside), now_inside) in self.zones.iter().zip(was_inside).zip(&now_inside) {
+                let (description, actions) = match (was_inside, now_inside) {
+                    (false, true) => ("Entered", &zone.on_enter),
+                    (true, false) => ("Left", &zone.on_exit),
+                    _ => continue,
+                };
+                println!("{} trigger zone {}", description, zone.name);
+                for action in actions {
+                    self.actions.send(action.clone())?;
+                }
+            }
+        }
+        self.inside = Some(now_inside);
+        Ok(())
+    }
+
+    fn handle_action(&mut self, _action: Action) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ZoneShape;
+    use crate::memory_backend::InMemoryBackend;
+    use crate::process_details::AddressType;
+    use process_memory::Architecture;
+    use std::sync::mpsc::{self, Receiver};
+
+    const X: usize = 0x100;
+    const Y: usize = 0x104;
+    const Z: usize = 0x108;
+
+    // A single zone around the origin which starts a segment on entry and stops it on exit
+    fn trigger_zone_handler(backend: &Rc<InMemoryBackend>) -> (TriggerZoneHandler, Receiver<Action>) {
+        let address_offsets: AddressOffsets = vec![
+            (AddressType::XPosition, vec![X]),
+            (AddressType::YPosition, vec![Y]),
+            (AddressType::ZPosition, vec![Z]),
+        ]
+        .into_iter()
+        .collect();
+        let memory: Rc<dyn MemoryBackend> = backend.clone();
+        let zones = vec![TriggerZone {
+            name: "Pit".to_string(),
+            game: None,
+            shape: ZoneShape::Sphere {
+                center: [0.0, 0.0, 0.0],
+                radius: 1.0,
+            },
+            on_enter: vec![Action::StartSegment {
+                name: Some("Pit jump".to_string()),
+            }],
+            on_exit: vec![Action::StopSegment {}],
+        }];
+        let (tx, rx) = mpsc::channel();
+        let handler = TriggerZoneHandler::new(&address_offsets, &0, &memory, &zones, "Tomb Raider", &tx).unwrap();
+        (handler, rx)
+    }
+
+    fn move_lara(backend: &InMemoryBackend, [x, y, z]: [f32; 3]) {
+        backend.set(X, x);
+        backend.set(Y, y);
+        backend.set(Z, z);
+    }
+
+    #[test]
+    fn sends_actions_on_entering_and_leaving() {
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let (mut handler, actions) = trigger_zone_handler(&backend);
+        move_lara(&backend, [5.0, 0.0, 0.0]);
+        handler.handle_tick().unwrap();
+
+        move_lara(&backend, [0.5, 0.0, 0.0]);
+        handler.handle_tick().unwrap();
+        handler.handle_tick().unwrap();
+        match actions.try_recv() {
+            Ok(Action::StartSegment { name }) => assert_eq!(name.as_deref(), Some("Pit jump")),
+            other => panic!("expected the enter action, got {:?}", other),
+        }
+        assert!(actions.try_recv().is_err());
+
+        move_lara(&backend, [5.0, 0.0, 0.0]);
+        handler.handle_tick().unwrap();
+        assert!(matches!(actions.try_recv(), Ok(Action::StopSegment {})));
+        assert!(actions.try_recv().is_err());
+    }
+
+    #[test]
+    fn starting_inside_a_zone_does_not_trigger_it() {
+        let backend = Rc::new(InMemoryBackend::new(Architecture::Arch64Bit));
+        let (mut handler, actions) = trigger_zone_handler(&backend);
+        move_lara(&backend, [0.5, 0.0, 0.0]);
+
+        handler.handle_tick().unwrap();
+        handler.handle_tick().unwrap();
+        assert!(actions.try_recv().is_err());
+
+        move_lara(&backend, [5.0, 0.0, 0.0]);
+        handler.handle_tick().unwrap();
+        assert!(matches!(actions.try_recv(), Ok(Action::StopSegment {})));
+    }
+}
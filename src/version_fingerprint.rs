@@ -0,0 +1,71 @@
+use crate::memory_backend::{read_value, MemoryBackend};
+use std::io;
+
+// Details of the loaded executable read from its PE headers. None of these depend on where the
+// image was loaded, so they are the same every time a given build is run.
+// https://learn.microsoft.com/en-us/windows/win32/debug/pe-format
+#[derive(Debug, Clone)]
+pub struct VersionFingerprint {
+    pub image_size: usize,
+    pub timestamp: u32,
+    pub checksum: u32,
+    pub sections: Vec<SectionFingerprint>,
+    // Hash of the whole section table, so builds with identical headers but differently laid
+    // out sections (e.g. Steam and Epic Games builds) can be told apart
+    pub section_table_hash: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct SectionFingerprint {
+    pub name: String,
+    pub hash: u64,
+}
+
+const SECTION_HEADER_SIZE: usize = 40;
+
+impl VersionFingerprint {
+    pub fn read(memory: &dyn MemoryBackend, base_addr: usize) -> io::Result<VersionFingerprint> {
+        let pe_header = base_addr + read_value::<u32>(memory, base_addr + 0x3C)? as usize;
+        if read_value::<[u8; 4]>(memory, pe_header)? != *b"PE\0\0" {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Missing PE signature"));
+        }
+
+        let coff_header = pe_header + 4;
+        let section_count = read_value::<u16>(memory, coff_header + 2)? as usize;
+        let timestamp = read_value::<u32>(memory, coff_header + 4)?;
+        let optional_header_size = read_value::<u16>(memory, coff_header + 16)? as usize;
+
+        let optional_header = coff_header + 20;
+        let image_size = read_value::<u32>(memory, optional_header + 0x38)? as usize;
+        let checksum = read_value::<u32>(memory, optional_header + 0x40)?;
+
+        let mut section_table = vec![0_u8; section_count * SECTION_HEADER_SIZE];
+        memory.read_bytes(optional_header + optional_header_size, &mut section_table)?;
+
+        let sections = section_table
+            .chunks(SECTION_HEADER_SIZE)
+            .map(|header| SectionFingerprint {
+                name: String::from_utf8_lossy(&header[0..8])
+                    .trim_end_matches('\0')
+                    .to_string(),
+                hash: fnv1a(header),
+            })
+            .collect();
+
+        Ok(VersionFingerprint {
+            image_size,
+            timestamp,
+            checksum,
+            sections,
+            section_table_hash: fnv1a(&section_table),
+        })
+    }
+}
+
+// A fixed, simple hash so that values written into version tables stay valid between builds of
+// tomb-helper (std's hashers make no such promise)
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
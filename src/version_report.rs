@@ -0,0 +1,232 @@
+use crate::find_process::{get_base_address, get_pid};
+use crate::memory_backend::{MemoryBackend, ProcessBackend};
+use crate::process_details::{AddressType, ProcessDetails};
+use crate::version_fingerprint::VersionFingerprint;
+use itertools::Itertools;
+use process_memory::{Pid, TryIntoProcessHandle};
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::time::{Duration, Instant};
+
+// The version string isn't in memory until the game has started up, so only report a process as
+// unknown once it has gone unrecognised for this long
+pub const UNKNOWN_VERSION_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+const MAX_VERSION_STRING_LENGTH: usize = 64;
+
+// Everything needed to add support for a version of a game that isn't in the version table
+#[derive(Debug, Clone)]
+pub struct VersionReport {
+    executable_name: String,
+    pid: Pid,
+    base_addr: usize,
+    fingerprint: Result<VersionFingerprint, String>,
+    // Known version, and the string found at that version's Version address
+    version_strings: Vec<(String, Result<String, String>)>,
+    closest_version: Option<String>,
+}
+
+impl VersionReport {
+    pub fn generate(possible_processes: &[ProcessDetails], executable_name: &str) -> Option<VersionReport> {
+        let candidates = possible_processes
+            .iter()
+            .filter(|details| details.executable_name == executable_name)
+            .collect::<Vec<_>>();
+        let pid = get_pid(executable_name)?;
+        let handle = pid.try_into_process_handle().ok()?;
+        let base_addr = get_base_address(pid) as *const _ as usize;
+
+        let fingerprint = VersionFingerprint::read(&ProcessBackend::new(handle, candidates.first()?.arch), base_addr)
+            .map_err(|e| e.to_string());
+
+        let version_strings = candidates
+            .iter()
+            .filter_map(|details| {
+                let offsets = details.address_offsets.get(&AddressType::Version)?;
+                let memory = ProcessBackend::new(handle, details.arch);
+                let found = read_version_string(&memory, offsets, base_addr, details.version.version.len())
+                    .map_err(|e| e.to_string());
+                Some((details.version.version.clone(), found))
+            })
+            .collect::<Vec<_>>();
+
+        let closest_version = candidates
+            .iter()
+            .max_by_key(|details| closeness(details, &fingerprint, &version_strings))
+            .map(|details| details.version.version.clone());
+
+        Some(VersionReport {
+            executable_name: executable_name.to_string(),
+            pid,
+            base_addr,
+            fingerprint,
+            version_strings,
+            closest_version,
+        })
+    }
+
+    pub fn write_to_file(&self, path: &str) -> io::Result<()> {
+        fs::write(path, self.to_string())
+    }
+
+    fn output(&self, output_file: &Option<String>) {
+        println!("{}", self);
+        if let Some(output_file) = output_file {
+            match self.write_to_file(output_file) {
+                Ok(()) => println!("Wrote version report to {}", output_file),
+                Err(msg) => eprintln!("Error: Could not write version report to {}: {}", output_file, msg),
+            }
+        }
+    }
+}
+
+fn executable_names(possible_processes: &[ProcessDetails]) -> Vec<String> {
+    possible_processes
+        .iter()
+        .map(|details| details.executable_name.clone())
+        .unique()
+        .collect()
+}
+
+// Reports on every running game straight away, whether or not its version is known
+pub fn report_running_games(possible_processes: &[ProcessDetails], output_file: &Option<String>) {
+    let reports = executable_names(possible_processes)
+        .iter()
+        .filter_map(|executable_name| VersionReport::generate(possible_processes, executable_name))
+        .collect::<Vec<_>>();
+
+    if reports.is_empty() {
+        println!("No Tomb Raider processes found");
+    }
+    for report in reports {
+        report.output(output_file);
+    }
+}
+
+impl fmt::Display for VersionReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Executable: {}", self.executable_name)?;
+        writeln!(f, "PID: {}", self.pid)?;
+        writeln!(f, "Base address: {:#x}", self.base_addr)?;
+        match &self.fingerprint {
+            Ok(fingerprint) => {
+                writeln!(f, "Image size (image_size): {}", fingerprint.image_size)?;
+                writeln!(f, "PE timestamp (timestamp): {}", fingerprint.timestamp)?;
+                writeln!(f, "PE checksum (checksum): {}", fingerprint.checksum)?;
+                writeln!(f, "Section table hash (section_table_hash): {}", fingerprint.section_table_hash)?;
+                for section in &fingerprint.sections {
+                    writeln!(f, "Section {:?} hash: {}", section.name, section.hash)?;
+                }
+            }
+            Err(msg) => writeln!(f, "Could not read PE headers: {}", msg)?,
+        }
+        for (version, found) in &self.version_strings {
+            match found {
+                Ok(found) => writeln!(f, "String at the {} version address: {:?}", version, found)?,
+                Err(msg) => writeln!(f, "String at the {} version address: unreadable ({})", version, msg)?,
+            }
+        }
+        match &self.closest_version {
+            Some(version) => writeln!(f, "Closest known version: {}", version),
+            None => writeln!(f, "Closest known version: none"),
+        }
+    }
+}
+
+// Reads up to the first NUL, falling back to exactly the known version's length in case the
+// longer read crosses into unreadable memory
+fn read_version_string(
+    memory: &dyn MemoryBackend,
+    offsets: &[usize],
+    base_addr: usize,
+    known_length: usize,
+) -> io::Result<String> {
+    let addr = memory.resolve_from_base(offsets, base_addr)?;
+
+    let mut bytes = vec![0_u8; MAX_VERSION_STRING_LENGTH];
+    if memory.read_bytes(addr, &mut bytes).is_err() {
+        bytes = vec![0_u8; known_length];
+        memory.read_bytes(addr, &mut bytes)?;
+    }
+
+    let length = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    Ok(String::from_utf8_lossy(&bytes[..length]).into_owned())
+}
+
+// Ranks by how many fingerprint fields match, then by how much of the version string matches
+fn closeness(
+    details: &ProcessDetails,
+    fingerprint: &Result<VersionFingerprint, String>,
+    version_strings: &[(String, Result<String, String>)],
+) -> (usize, usize) {
+    let fingerprint_matches = match fingerprint {
+        Ok(fingerprint) => [
+            details.version.image_size.map(|v| v == fingerprint.image_size),
+            details.version.timestamp.map(|v| v == fingerprint.timestamp),
+            details.version.checksum.map(|v| v == fingerprint.checksum),
+            details.version.section_table_hash.map(|v| v == fingerprint.section_table_hash),
+        ]
+        .iter()
+        .filter(|matched| **matched == Some(true))
+        .count(),
+        Err(_) => 0,
+    };
+
+    let string_match = version_strings
+        .iter()
+        .find(|(version, _)| *version == details.version.version)
+        .and_then(|(_, found)| found.as_ref().ok())
+        .map_or(0, |found| {
+            found
+                .chars()
+                .zip(details.version.version.chars())
+                .take_while(|(a, b)| a == b)
+                .count()
+        });
+
+    (fingerprint_matches, string_match)
+}
+
+// Prints a report once for each running game process that stays unrecognised
+#[derive(Debug, Default)]
+pub struct UnknownVersionReporter {
+    output_file: Option<String>,
+    first_seen: HashMap<Pid, Instant>,
+    reported: HashSet<Pid>,
+}
+
+impl UnknownVersionReporter {
+    pub fn new(output_file: Option<String>) -> UnknownVersionReporter {
+        UnknownVersionReporter {
+            output_file,
+            ..Default::default()
+        }
+    }
+
+    pub fn check(&mut self, possible_processes: &[ProcessDetails]) {
+        for executable_name in executable_names(possible_processes) {
+            let pid = match get_pid(&executable_name) {
+                Some(pid) => pid,
+                None => continue,
+            };
+
+            let first_seen = *self.first_seen.entry(pid).or_insert_with(Instant::now);
+            if self.reported.contains(&pid) || first_seen.elapsed() < UNKNOWN_VERSION_GRACE_PERIOD {
+                continue;
+            }
+            self.reported.insert(pid);
+
+            let report = match VersionReport::generate(possible_processes, &executable_name) {
+                Some(report) => report,
+                None => continue,
+            };
+            println!(
+                "Found {} but did not recognise its version, please report the following so it can be supported:",
+                executable_name
+            );
+            report.output(&self.output_file);
+        }
+    }
+}